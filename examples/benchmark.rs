@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use cand::Logger;
+use cand::{BufferedStorageProvider, Logger};
 
 fn main() {
     let time = Instant::now();
@@ -10,9 +10,24 @@ fn main() {
     for i in 1..=1_000_000 {
         logger.log_info(format!("Logger no: {}", i));
     }
+    let unbuffered = time.elapsed();
+
+    let time = Instant::now();
+    // Same output, handed to () in 64 KiB batches.
+    let mut buffered = Logger(Instant::now(), BufferedStorageProvider::new((), 64 * 1024));
+
+    for i in 1..=1_000_000 {
+        buffered.log_info(format!("Logger no: {}", i));
+    }
+    buffered.flush();
+    let batched = time.elapsed();
 
     logger.log_ok(format_args!(
         "Time taken in {:?} to log info 1_000_000",
-        time.elapsed()
+        unbuffered
+    ));
+    logger.log_ok(format_args!(
+        "Time taken in {:?} to log info 1_000_000 buffered",
+        batched
     ));
 }
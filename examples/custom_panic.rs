@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use cand::{Logger, StorageProvider, black_box_cand};
+use cand::{Logger, StorageProvider, black_box_cand, strip_ansi};
 
 impl StorageProvider for LogErrorStorage {
     fn write_data(&mut self, args: std::fmt::Arguments, _debuglevel: &cand::StatusLevel) {
@@ -13,23 +13,6 @@ impl StorageProvider for LogErrorStorage {
     }
 }
 
-fn strip_ansi(s: &str) -> String {
-    let mut result = String::new();
-    let mut in_esc = false;
-    for c in s.chars() {
-        if c == '\x1b' {
-            in_esc = true;
-        } else if in_esc {
-            if c == 'm' {
-                in_esc = false;
-            }
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
 fn main() {
     println!("Hello, world!");
 
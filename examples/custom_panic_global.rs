@@ -1,11 +1,11 @@
 use std::{sync::Mutex, time::Instant};
 
-use cand::{Logger, StorageProvider, TimeProvider, black_box_cand_global};
+use cand::{Logger, StorageProvider, TimeProvider, black_box_cand_global, strip_ansi};
 use once_cell::sync::Lazy;
 
 static LOGGER: Lazy<Mutex<Logger<LocalTime, LogErrorStorage>>> = Lazy::new(|| {
     Mutex::new(Logger(
-        LocalTime(Lazy::new(|| Instant::now())),
+        LocalTime(Lazy::new(Instant::now)),
         LogErrorStorage {},
     ))
 });
@@ -14,7 +14,7 @@ pub struct LocalTime(pub Lazy<Instant>);
 
 impl TimeProvider for LocalTime {
     fn now() -> Self {
-        LocalTime(Lazy::new(|| Instant::now()))
+        LocalTime(Lazy::new(Instant::now))
     }
     fn elapsed(&self) -> core::time::Duration {
         self.0.elapsed()
@@ -36,23 +36,6 @@ impl StorageProvider for LogErrorStorage {
     }
 }
 
-fn strip_ansi(s: &str) -> String {
-    let mut result = String::new();
-    let mut in_esc = false;
-    for c in s.chars() {
-        if c == '\x1b' {
-            in_esc = true;
-        } else if in_esc {
-            if c == 'm' {
-                in_esc = false;
-            }
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
 fn main() {
     println!("Hello, world!");
     black_box_cand_global!(&LOGGER);
@@ -1,9 +1,9 @@
-use std::{error::Error, io, time::Instant};
+use std::{error::Error, io};
 
 use cand::{Logger, StorageProvider, TimeProvider};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut logger = Logger(Instant::now(), ());
+    let mut logger = Logger::stdout_timed();
 
     // Get file path from command-line arguments or prompt user
     let args: Vec<String> = std::env::args().collect();
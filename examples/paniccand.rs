@@ -1,7 +1,6 @@
 use cand::{Logger, black_box_cand};
 use reqwest::Client;
 use std::time::Instant;
-use tokio;
 
 #[tokio::main]
 async fn main() {
@@ -16,14 +16,14 @@ fn ai_generated_showcase() {
     let mut ai_generator = AIMessageGenerator::new();
 
     // 🤖 **AI-Generated Header**
-    logger.log_ok(&ai_generator.generate_startup_message());
-    logger.log_info(&ai_generator.generate_system_context());
+    logger.log_ok(ai_generator.generate_startup_message());
+    logger.log_info(ai_generator.generate_system_context());
     println!();
 
     // 🧠 **AI Feature Analysis**
     logger.log_info("🧠 AI Analysis: Evaluating CAND capabilities...");
     for feature in ai_generator.analyze_features() {
-        logger.log_ok(&format!("  ✨ {}", feature));
+        logger.log_ok(format!("  ✨ {}", feature));
         thread::sleep(Duration::from_millis(150));
     }
     println!();
@@ -47,7 +47,7 @@ fn ai_generated_showcase() {
     logger.log_info("🔮 AI Predictive Analysis:");
     let predictions = ai_generator.generate_predictions();
     for prediction in predictions {
-        logger.log_warn(&format!("  🔍 {}", prediction));
+        logger.log_warn(format!("  🔍 {}", prediction));
         thread::sleep(Duration::from_millis(200));
     }
     println!();
@@ -64,7 +64,7 @@ fn ai_generated_showcase() {
     logger.log_info("📊 AI Performance Insights:");
     let insights = ai_generator.generate_performance_insights();
     for insight in insights {
-        logger.log_ok(&format!("  📈 {}", insight));
+        logger.log_ok(format!("  📈 {}", insight));
         thread::sleep(Duration::from_millis(100));
     }
     println!();
@@ -73,12 +73,12 @@ fn ai_generated_showcase() {
     logger.log_info("🎯 AI Recommendations for Your Project:");
     let recommendations = ai_generator.generate_usage_recommendations();
     for rec in recommendations {
-        logger.log_info(&format!("  💡 {}", rec));
+        logger.log_info(format!("  💡 {}", rec));
     }
     println!();
 
     // ✨ **AI-Generated Conclusion**
-    logger.log_ok(&ai_generator.generate_conclusion());
+    logger.log_ok(ai_generator.generate_conclusion());
     logger.log_info("🤖 AI-powered logging demo complete!");
 }
 
@@ -87,9 +87,9 @@ fn recovery_handler(mut logger: Logger<(), ()>) {
     let ai_error = "sensor disconnected"; // Hardcode or pass differently if needed; avoid capturing
     let mut ai_generator = AIMessageGenerator::new(); // Recreate inside fn
     let recovery_plan = ai_generator.generate_recovery_plan(ai_error);
-    logger.log_warn(&format!("  🤖 AI Analysis: {}", recovery_plan.analysis));
-    logger.log_info(&format!("  🔄 Executing: {}", recovery_plan.action));
-    logger.log_ok(&format!("  ✅ Result: {}", recovery_plan.outcome));
+    logger.log_warn(format!("  🤖 AI Analysis: {}", recovery_plan.analysis));
+    logger.log_info(format!("  🔄 Executing: {}", recovery_plan.action));
+    logger.log_ok(format!("  ✅ Result: {}", recovery_plan.outcome));
 }
 
 // AI Message Generator - Simulates intelligent message generation
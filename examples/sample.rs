@@ -11,7 +11,7 @@ fn main() {
 }
 
 fn showcase_cand() {
-    let mut logger = Logger((), ());
+    let mut logger = Logger::stdout();
 
     // 🔥 **Eye-catching header**
     logger.log_ok("🔥 CAND - Colorful And Nice Debugging 🔥");
@@ -60,7 +60,7 @@ fn showcase_cand() {
 
     // 🎮 **Getting started**
     logger.log_info("🎮 Quick Start (2 lines):");
-    logger.log_ok("  let mut logger = Logger((), ());");
+    logger.log_ok("  let mut logger = Logger::stdout();");
     logger.log_ok("  logger.log_ok(\"🚀 Ready!\");");
     println!();
 
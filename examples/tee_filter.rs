@@ -0,0 +1,18 @@
+use std::time::Instant;
+
+use cand::{FileStorage, LevelFilter, Logger, StatusLevel, TeeStorage};
+
+fn main() -> std::io::Result<()> {
+    // Console shows everything, warnings.log only receives Warning and above.
+    let path = std::env::temp_dir().join("warnings.log");
+    let file = LevelFilter::new(FileStorage::new(&path)?, StatusLevel::Warning);
+    let mut logger = Logger(Instant::now(), TeeStorage((), file));
+
+    logger.log_info("Only on the console");
+    logger.log_ok("Only on the console");
+    logger.log_warn("On the console and in warnings.log");
+    logger.log_err("On the console and in warnings.log");
+
+    println!("Wrote {}", path.display());
+    Ok(())
+}
@@ -6,7 +6,7 @@ use core::fmt::{Debug, Display};
 use core::time::Duration;
 
 #[cfg(feature = "ufmt")]
-use ufmt::{uDebug, uWrite};
+use ufmt::{uDebug, uDisplay, uWrite};
 
 macro_rules! define_colors {
     ($($name:ident => $color:expr),* $(,)?) => {
@@ -19,8 +19,23 @@ macro_rules! define_colors {
     };
 }
 
+/// 24-bit foreground escape code for `r, g, b` literals, e.g. `rgb!(255, 165, 0)`
+/// for orange, usable in a `static` [`ColorTheme`].
+#[cfg(feature = "truecolor")]
+#[macro_export]
+macro_rules! rgb {
+    ($r:literal, $g:literal, $b:literal) => {
+        concat!("\x1b[38;2;", $r, ";", $g, ";", $b, "m")
+    };
+}
+
 define_colors! {
     RESET => "\x1b[0m",
+    DIM => "\x1b[2m",
+}
+
+#[cfg(not(feature = "truecolor"))]
+define_colors! {
     LIGHT_GREEN => "\x1b[92m",
     LIGHT_BLUE => "\x1b[94m",
     LIGHT_RED => "\x1b[91m",
@@ -28,6 +43,328 @@ define_colors! {
     RED => "\x1b[31m",
 }
 
+#[cfg(feature = "truecolor")]
+define_colors! {
+    LIGHT_GREEN => rgb!(80, 200, 120),
+    LIGHT_BLUE => rgb!(90, 160, 255),
+    LIGHT_RED => rgb!(255, 85, 85),
+    LIGHT_YELLOW => rgb!(255, 165, 0),
+    RED => "\x1b[1;38;2;220;20;60m",
+}
+
+/// When color codes are written; only matters with the `colors` feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colors unless `NO_COLOR` is set or stdout is not a terminal (std only;
+    /// without std this is the same as `Always`).
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+static COLOR_MODE: core::sync::atomic::AtomicU8 =
+    core::sync::atomic::AtomicU8::new(ColorMode::Auto as u8);
+
+/// Sets the [`ColorMode`] for every logger in the process.
+///
+/// ```
+/// use cand::{set_color_mode, ColorMode, Logger, StatusLevel, VecStorage};
+///
+/// let mut logger = Logger((), VecStorage::new());
+/// set_color_mode(ColorMode::Never);
+/// logger.logdisp(StatusLevel::Error, "plain");
+/// assert_eq!(logger.1.as_str(), "E&: plain\n");
+///
+/// logger.1.clear();
+/// set_color_mode(ColorMode::Always);
+/// logger.logdisp(StatusLevel::Error, "colored");
+/// assert_eq!(logger.1.as_str().contains("\x1b["), cfg!(feature = "colors"));
+/// ```
+pub fn set_color_mode(mode: ColorMode) {
+    COLOR_MODE.store(mode as u8, core::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn color_mode() -> ColorMode {
+    match COLOR_MODE.load(core::sync::atomic::Ordering::Relaxed) {
+        1 => ColorMode::Always,
+        2 => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+fn colors_enabled() -> bool {
+    cfg!(feature = "colors")
+        && match color_mode() {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => auto_colors(),
+        }
+}
+
+/// `NO_COLOR` and terminal detection, checked once and cached.
+#[cfg(feature = "std")]
+fn auto_colors() -> bool {
+    use core::sync::atomic::{AtomicU8, Ordering};
+    // 0 = not checked yet, 1 = colors, 2 = no colors.
+    static DETECTED: AtomicU8 = AtomicU8::new(0);
+    match DETECTED.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            let colors = std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && std::io::IsTerminal::is_terminal(&std::io::stdout());
+            DETECTED.store(if colors { 1 } else { 2 }, Ordering::Relaxed);
+            colors
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn auto_colors() -> bool {
+    true
+}
+
+/// ANSI sequence written before each level's prefix and message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorTheme {
+    pub ok: &'static str,
+    pub info: &'static str,
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub critical: &'static str,
+}
+
+impl ColorTheme {
+    /// The bright palette used unless another theme is set; 24-bit colors with
+    /// the `truecolor` feature, e.g. orange for `Warning`.
+    pub const DEFAULT: ColorTheme = ColorTheme {
+        ok: LIGHT_GREEN,
+        info: LIGHT_BLUE,
+        error: LIGHT_RED,
+        warning: LIGHT_YELLOW,
+        critical: RED,
+    };
+
+    /// Darker colors that stay readable on light terminal backgrounds.
+    pub const LIGHT: ColorTheme = ColorTheme {
+        ok: "\x1b[32m",
+        info: "\x1b[34m",
+        error: "\x1b[31m",
+        warning: "\x1b[33m",
+        critical: "\x1b[1;31m",
+    };
+
+    /// No escape codes at all, not even resets: plain text at runtime, e.g. for
+    /// CI logs, while the `colors` feature stays enabled.
+    ///
+    /// ```
+    /// use cand::{ColorTheme, StatusLevel};
+    ///
+    /// assert_eq!(ColorTheme::NONE.prefix(StatusLevel::Error), "");
+    /// assert_eq!(ColorTheme::NONE.suffix(), "");
+    /// assert_eq!(ColorTheme::DEFAULT.suffix(), "\x1b[0m");
+    /// ```
+    pub const NONE: ColorTheme = ColorTheme {
+        ok: "",
+        info: "",
+        error: "",
+        warning: "",
+        critical: "",
+    };
+
+    pub const fn light() -> Self {
+        Self::LIGHT
+    }
+
+    /// Written before a message at `level`, same as [`color`](Self::color).
+    pub const fn prefix(&self, level: StatusLevel) -> &'static str {
+        self.color(level)
+    }
+
+    /// Written after each message: the reset code, or `""` for a theme without
+    /// any codes such as [`NONE`](Self::NONE).
+    pub const fn suffix(&self) -> &'static str {
+        if self.is_plain() { "" } else { RESET }
+    }
+
+    const fn is_plain(&self) -> bool {
+        self.ok.is_empty()
+            && self.info.is_empty()
+            && self.error.is_empty()
+            && self.warning.is_empty()
+            && self.critical.is_empty()
+    }
+
+    pub const fn color(&self, level: StatusLevel) -> &'static str {
+        match level {
+            StatusLevel::Ok => self.ok,
+            StatusLevel::Info => self.info,
+            StatusLevel::Error => self.error,
+            StatusLevel::Warning => self.warning,
+            StatusLevel::Critical => self.critical,
+        }
+    }
+}
+
+/// Per-level RGB colors, turned into a [`ColorTheme`] at runtime, e.g. from a
+/// config file. For a fixed palette, a `static` theme built with [`rgb!`] avoids
+/// the allocation.
+///
+/// ```
+/// use cand::{set_color_theme, ColorConfig};
+///
+/// let config = ColorConfig { warning: [255, 140, 0], ..ColorConfig::DEFAULT };
+/// let theme = config.leak();
+/// assert_eq!(theme.warning, "\x1b[38;2;255;140;0m");
+/// set_color_theme(theme);
+/// ```
+#[cfg(feature = "truecolor")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorConfig {
+    pub ok: [u8; 3],
+    pub info: [u8; 3],
+    pub error: [u8; 3],
+    pub warning: [u8; 3],
+    pub critical: [u8; 3],
+}
+
+#[cfg(feature = "truecolor")]
+impl ColorConfig {
+    /// The colors of [`ColorTheme::DEFAULT`] with `truecolor`, which also makes `Critical` bold.
+    pub const DEFAULT: ColorConfig = ColorConfig {
+        ok: [80, 200, 120],
+        info: [90, 160, 255],
+        error: [255, 85, 85],
+        warning: [255, 165, 0],
+        critical: [220, 20, 60],
+    };
+
+    /// Renders the escape codes and leaks them with the theme, so the result
+    /// can go to [`set_color_theme`]; meant to be called once at startup.
+    #[cfg(feature = "alloc")]
+    pub fn leak(&self) -> &'static ColorTheme {
+        use alloc::boxed::Box;
+        let code = |[r, g, b]: [u8; 3]| -> &'static str {
+            alloc::format!("\x1b[38;2;{r};{g};{b}m").leak()
+        };
+        Box::leak(Box::new(ColorTheme {
+            ok: code(self.ok),
+            info: code(self.info),
+            error: code(self.error),
+            warning: code(self.warning),
+            critical: code(self.critical),
+        }))
+    }
+}
+
+#[cfg(feature = "truecolor")]
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Null until [`set_color_theme`] is called, meaning [`ColorTheme::DEFAULT`].
+static COLOR_THEME: core::sync::atomic::AtomicPtr<ColorTheme> =
+    core::sync::atomic::AtomicPtr::new(core::ptr::null_mut());
+
+/// Sets the [`ColorTheme`] for every logger in the process, e.g.
+/// `set_color_theme(&ColorTheme::LIGHT)`.
+///
+/// ```
+/// use cand::{set_color_mode, set_color_theme, ColorMode, ColorTheme, Logger, StatusLevel, VecStorage};
+///
+/// static THEME: ColorTheme = ColorTheme { warning: "\x1b[35m", ..ColorTheme::LIGHT };
+/// set_color_mode(ColorMode::Always);
+/// set_color_theme(&THEME);
+/// let mut logger = Logger((), VecStorage::new());
+/// logger.logdisp(StatusLevel::Warning, "disk almost full");
+/// assert_eq!(logger.1.as_str(), "\x1b[35mW&:\x1b[0m \x1b[35mdisk almost full\x1b[0m\n");
+/// ```
+pub fn set_color_theme(theme: &'static ColorTheme) {
+    COLOR_THEME.store(
+        (theme as *const ColorTheme).cast_mut(),
+        core::sync::atomic::Ordering::Release,
+    );
+}
+
+pub fn color_theme() -> &'static ColorTheme {
+    let theme = COLOR_THEME.load(core::sync::atomic::Ordering::Acquire);
+    // SAFETY: only ever set from a `&'static ColorTheme` in `set_color_theme`.
+    unsafe { theme.as_ref() }.unwrap_or(&ColorTheme::DEFAULT)
+}
+
+/// The reset code if colors are currently enabled, otherwise `""`.
+fn reset() -> &'static str {
+    if colors_enabled() {
+        color_theme().suffix()
+    } else {
+        ""
+    }
+}
+
+fn dim() -> &'static str {
+    if colors_enabled() && !color_theme().is_plain() {
+        DIM
+    } else {
+        ""
+    }
+}
+
+/// Where a message was logged, as captured by [`log!`] or [`here!`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: &'static str,
+    pub line: u32,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// `message (file:line)` with the location dimmed, the message of `log_at`.
+struct Located<'a, D> {
+    message: D,
+    location: &'a SourceLocation,
+}
+
+impl<D: Display> Display for Located<'_, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {}({})", self.message, dim(), self.location)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<D: uDebug> uDebug for Located<'_, D> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        uDebug::fmt(&self.message, f)?;
+        f.write_str(" ")?;
+        f.write_str(dim())?;
+        f.write_str("(")?;
+        f.write_str(self.location.file)?;
+        f.write_str(":")?;
+        uDisplay::fmt(&self.location.line, f)?;
+        f.write_str(")")
+    }
+}
+
+/// Level of a log message.
+///
+/// Levels compare by [`severity`](StatusLevel::severity), so filters such as
+/// `level >= StatusLevel::Warning` behave as expected even though the
+/// discriminants list `Error` before `Warning`.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum StatusLevel {
     Ok = 0,
@@ -38,25 +375,81 @@ pub enum StatusLevel {
 }
 
 impl StatusLevel {
-    fn to_color(&self) -> &'static str {
+    /// Escape code for the level, or `""` if colors are currently disabled.
+    fn to_color(self) -> &'static str {
+        if !colors_enabled() {
+            return "";
+        }
+        color_theme().color(self)
+    }
+
+    /// Full upper-case name of the level, e.g. `"WARNING"`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            StatusLevel::Ok => "OK",
+            StatusLevel::Info => "INFO",
+            StatusLevel::Error => "ERROR",
+            StatusLevel::Warning => "WARNING",
+            StatusLevel::Critical => "CRITICAL",
+        }
+    }
+
+    /// Rank used for ordering levels: `Ok < Info < Warning < Error < Critical`.
+    ///
+    /// The discriminants keep their historical values, which put `Error` below
+    /// `Warning`, so comparisons and filters go through this instead.
+    pub const fn severity(&self) -> u8 {
+        match self {
+            StatusLevel::Ok => 0,
+            StatusLevel::Info => 1,
+            StatusLevel::Warning => 2,
+            StatusLevel::Error => 3,
+            StatusLevel::Critical => 4,
+        }
+    }
+
+    /// `true` for `Error` and `Critical`, e.g. to ring an alarm.
+    pub const fn is_error_or_above(&self) -> bool {
+        self.severity() >= StatusLevel::Error.severity()
+    }
+
+    /// `true` for `Ok` and `Info`, the levels that report normal operation.
+    pub const fn is_nominal(&self) -> bool {
+        self.severity() <= StatusLevel::Info.severity()
+    }
+
+    /// Syslog severity code: `Ok` and `Info` are 6 (informational), `Warning`
+    /// 4, `Error` 3 and `Critical` 2.
+    pub const fn syslog_severity(&self) -> u8 {
         match self {
-            StatusLevel::Ok => LIGHT_GREEN,
-            StatusLevel::Info => LIGHT_BLUE,
-            StatusLevel::Error => LIGHT_RED,
-            StatusLevel::Warning => LIGHT_YELLOW,
-            StatusLevel::Critical => RED,
+            StatusLevel::Ok | StatusLevel::Info => 6,
+            StatusLevel::Warning => 4,
+            StatusLevel::Error => 3,
+            StatusLevel::Critical => 2,
         }
     }
 }
 
+impl PartialOrd for StatusLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StatusLevel {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.severity().cmp(&other.severity())
+    }
+}
+
 macro_rules! impl_status_format {
     (
         $self:expr,$formatter:ident, $write_macro:ident,
-        $($variant:ident => $symbol:expr, $color:expr),* $(,)?
+        $($variant:ident => $symbol:expr),* $(,)?
     ) => {
         match $self {
             $(
-                StatusLevel::$variant => $write_macro!($formatter, "{}{}&:{}", $color, $symbol, RESET)?,
+                StatusLevel::$variant => $write_macro!($formatter, "{}{}&:{}", $self.to_color(), $symbol, reset())?,
             )*
         }
     };
@@ -71,11 +464,11 @@ impl uDebug for StatusLevel {
         use ufmt::uwrite;
 
         impl_status_format!(self,f, uwrite,
-            Ok => "O", LIGHT_GREEN,
-            Info => "I", LIGHT_BLUE,
-            Error => "E", LIGHT_RED,
-            Warning => "W", LIGHT_YELLOW,
-            Critical => "C", RED,
+            Ok => "O",
+            Info => "I",
+            Error => "E",
+            Warning => "W",
+            Critical => "C",
         );
 
         Ok(())
@@ -85,241 +478,6529 @@ impl uDebug for StatusLevel {
 impl Debug for StatusLevel {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         impl_status_format!(self,f, write,
-            Ok => "O", LIGHT_GREEN,
-            Info => "I", LIGHT_BLUE,
-            Error => "E", LIGHT_RED,
-            Warning => "W", LIGHT_YELLOW,
-            Critical => "C", RED,
+            Ok => "O",
+            Info => "I",
+            Error => "E",
+            Warning => "W",
+            Critical => "C",
         );
         Ok(())
     }
 }
 
-#[cfg(feature = "alloc")]
-extern crate alloc;
-#[cfg(feature = "alloc")]
-use alloc::boxed::Box;
+impl From<StatusLevel> for u8 {
+    fn from(level: StatusLevel) -> u8 {
+        level as u8
+    }
+}
 
-#[cfg(feature = "ufmt")]
-pub trait UStorageProvider {
-    fn write_data(&mut self, d: impl uDebug);
+impl TryFrom<u8> for StatusLevel {
+    type Error = InvalidStatusLevel;
+
+    fn try_from(value: u8) -> Result<Self, InvalidStatusLevel> {
+        match value {
+            0 => Ok(StatusLevel::Ok),
+            1 => Ok(StatusLevel::Info),
+            2 => Ok(StatusLevel::Error),
+            3 => Ok(StatusLevel::Warning),
+            4 => Ok(StatusLevel::Critical),
+            _ => Err(InvalidStatusLevel),
+        }
+    }
 }
 
-use core::fmt::Arguments;
+#[cfg(feature = "serde")]
+const LEVEL_NAMES: [&str; 5] = ["Ok", "Info", "Error", "Warning", "Critical"];
 
-pub trait StorageProvider {
-    /// Write log data directly - single responsibility
-    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel);
+/// The variant name (`"Warning"`) for human-readable formats such as JSON, the
+/// numeric value (`3`) for binary formats.
+#[cfg(feature = "serde")]
+impl serde::Serialize for StatusLevel {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let index = *self as u8;
+            serializer.serialize_unit_variant(
+                "StatusLevel",
+                index.into(),
+                LEVEL_NAMES[usize::from(index)],
+            )
+        } else {
+            serializer.serialize_u8(*self as u8)
+        }
+    }
 }
 
-#[cfg(feature = "std")]
-impl StorageProvider for () {
-    fn write_data(&mut self, args: Arguments<'_>, _debuglevel: &StatusLevel) {
-        print!("{args}")
+/// Human-readable formats accept the variant name in any case (`"Warning"`,
+/// `"warning"`) as well as its numeric value (`3`), so the output of
+/// [`JsonStorage`] and `Display` reads back. Binary formats read the number
+/// written by `Serialize`.
+///
+/// ```
+/// use cand::StatusLevel;
+///
+/// for level in [StatusLevel::Ok, StatusLevel::Warning, StatusLevel::Critical] {
+///     let bytes = bincode::serialize(&level).unwrap();
+///     assert_eq!(bytes, [u8::from(level)]);
+///     assert_eq!(bincode::deserialize::<StatusLevel>(&bytes).unwrap(), level);
+/// }
+/// ```
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StatusLevel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LevelVisitor;
+
+        impl serde::de::Visitor<'_> for LevelVisitor {
+            type Value = StatusLevel;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("a status level name or number")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<StatusLevel, E> {
+                v.parse().map_err(|_| E::unknown_variant(v, &LEVEL_NAMES))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<StatusLevel, E> {
+                u8::try_from(v)
+                    .ok()
+                    .and_then(|v| StatusLevel::try_from(v).ok())
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(LevelVisitor)
+        } else {
+            deserializer.deserialize_u8(LevelVisitor)
+        }
     }
 }
 
-pub trait TimeProvider {
-    fn now() -> Self;
-    fn elapsed(&self) -> core::time::Duration;
-    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result;
+/// Parses the full name (`"warning"`) or the `Debug` letter (`"W"`), ignoring case.
+impl core::str::FromStr for StatusLevel {
+    type Err = InvalidStatusLevel;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        (0..=4)
+            .filter_map(|value| StatusLevel::try_from(value).ok())
+            .find(|level| {
+                let name = level.as_str();
+                name.eq_ignore_ascii_case(s) || (s.len() == 1 && name[..1].eq_ignore_ascii_case(s))
+            })
+            .ok_or(InvalidStatusLevel)
+    }
 }
 
-#[cfg(feature = "std")]
-use std::time::Instant;
+/// Error returned when a value does not name a [`StatusLevel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidStatusLevel;
 
-#[cfg(feature = "std")]
-impl TimeProvider for Instant {
-    fn now() -> Self {
-        Instant::now()
+impl Display for InvalidStatusLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("invalid status level")
     }
-    fn elapsed(&self) -> core::time::Duration {
-        self.elapsed()
+}
+
+impl core::error::Error for InvalidStatusLevel {}
+
+/// Writes the full name from [`StatusLevel::as_str`], honoring width and alignment.
+impl Display for StatusLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.pad(self.as_str())
     }
-    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(f, "{:?}:", self.elapsed())?;
-        Ok(())
+}
+
+#[cfg(feature = "ufmt")]
+impl uDisplay for StatusLevel {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str(self.as_str())
     }
 }
 
-impl TimeProvider for () {
-    fn now() -> Self {}
-    fn elapsed(&self) -> core::time::Duration {
-        core::time::Duration::ZERO
+/// Colored full-name level prefix used by the `*_long` log methods.
+struct LongLevel(StatusLevel);
+
+impl Display for LongLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}:{}", self.0.to_color(), self.0, reset())
     }
-    fn write(&self, _f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        Ok(())
+}
+
+/// Horizontal rule written by `log_separator`, 40 columns wide.
+const SEPARATOR: &str = "────────────────────────────────────────";
+
+/// `text` centered in a [`SEPARATOR`]-wide rule, e.g. `──── Phase 2 ────`.
+struct Header<'a>(&'a str);
+
+impl Display for Header<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let rule = |columns: usize| &SEPARATOR[..columns * '─'.len_utf8()];
+        let fill = 40usize.saturating_sub(self.0.chars().count() + 2);
+        write!(f, "{} {} {}", rule(fill / 2), self.0, rule(fill - fill / 2))
     }
 }
 
-macro_rules! impl_log_methods {
-    ($($method:ident => $level:expr),* $(,)?) => {
-        $(
-            pub fn $method(&mut self, args: impl Display) {
-                self.logdisp($level, args);
-            }
-        )*
-    };
+/// Cells in the bar drawn by `log_progress`.
+const PROGRESS_WIDTH: usize = 20;
+
+/// `label [########··········] 42%`, drawn into a stack buffer.
+struct Progress<'a> {
+    current: u64,
+    total: u64,
+    label: &'a str,
 }
 
-macro_rules! impl_try_get {
-    ($error_bound:path, owned) => {
-        #[cfg(feature = "std")]
-        #[cfg(feature = "ufmt")]
-        pub fn try_get<O>(
-            mut self, // Takes ownership
-            tryresult: Result<O, E: Debug>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            match tryresult {
-                Ok(x) => (x, self),
-                Err(err) => {
-                    self.log(StatusLevel::Warning, UDebugStr(&err.to_string()));
-                    redirectfn(self);
-                    std::process::exit(1);
-                }
+impl Display for Progress<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let ratio = |scale: u64| match self.total {
+            0 => scale,
+            total => {
+                (u128::from(self.current.min(total)) * u128::from(scale) / u128::from(total)) as u64
             }
+        };
+        let filled = ratio(PROGRESS_WIDTH as u64) as usize;
+        let mut buf = [0u8; PROGRESS_WIDTH * 2];
+        let mut len = 0;
+        for cell in 0..PROGRESS_WIDTH {
+            len += if cell < filled { '#' } else { '·' }
+                .encode_utf8(&mut buf[len..])
+                .len();
         }
+        let bar = core::str::from_utf8(&buf[..len]).map_err(|_| core::fmt::Error)?;
+        write!(f, "{} [{}] {}%", self.label, bar, ratio(100))
+    }
+}
 
-        #[cfg(feature = "std")]
-        #[cfg(not(feature = "ufmt"))]
-        pub fn try_get<O>(
-            mut self, // Takes ownership
-            tryresult: Result<O, Box<dyn core::error::Error>>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            match tryresult {
-                Ok(x) => (x, self),
-                Err(err) => {
-                    self.log(StatusLevel::Warning, err);
-                    redirectfn(self);
-                    std::process::exit(1);
-                }
+/// Bytes as lowercase hex, e.g. `aa bb cc`, or `aabbcc` when `compact`.
+struct HexBytes<'a> {
+    bytes: &'a [u8],
+    compact: bool,
+}
+
+impl Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if i > 0 && !self.compact {
+                f.write_str(" ")?;
             }
+            write!(f, "{byte:02x}")?;
         }
+        Ok(())
+    }
+}
 
-        #[cfg(not(feature = "std"))]
-        pub fn try_get<O, E: $error_bound>(
-            mut self, // Takes ownership
-            tryresult: Result<O, E>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            match tryresult {
-                Ok(x) => (x, self),
-                Err(err) => {
-                    self.log(StatusLevel::Warning, err);
-                    redirectfn(self);
-                    loop {}
-                }
-            }
+/// Bytes shown by `log_hex` before the dump is cut off with `… (N more bytes)`.
+pub const HEX_DUMP_MAX: usize = 256;
+
+/// Canonical hex dump, the message of `log_hex`:
+///
+/// ```text
+/// label (17 bytes)
+///     00000000  48 65 6c 6c 6f 20 77 6f  72 6c 64 21 0a 00 01 02  |Hello world!....|
+///     00000010  ff                                                |.|
+/// ```
+///
+/// `indent` is the width of the line prefix, so continuation lines start under
+/// the label; it is read when the dump is written. Written with `write_str`
+/// only, shared by `Display` and ufmt.
+struct HexDump<'a> {
+    label: &'a str,
+    bytes: &'a [u8],
+    indent: &'a core::cell::Cell<usize>,
+    max: usize,
+}
+
+/// Writes `n` in decimal into the end of `buf`, returning the digits.
+fn decimal(mut n: usize, buf: &mut [u8; 20]) -> &str {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
         }
-    };
+    }
+    core::str::from_utf8(&buf[start..]).unwrap_or_default()
+}
 
-    ($error_bound:path, cloned) => {
-        #[cfg(feature = "std")]
-        #[cfg(not(feature = "ufmt"))]
-        pub fn try_get<O>(
-            &mut self, // Takes reference
-            tryresult: Result<O, Box<dyn core::error::Error>>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            let mut new_self = self.clone();
-            match tryresult {
-                Ok(x) => (x, new_self),
-                Err(err) => {
-                    new_self.log(StatusLevel::Warning, err);
-                    redirectfn(new_self);
-                    std::process::exit(1);
+macro_rules! write_hex_dump {
+    ($f:ident, $dump:expr) => {{
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        // `buf` only ever holds ASCII.
+        let dump: &HexDump = $dump;
+        let shown = &dump.bytes[..dump.bytes.len().min(dump.max)];
+        let indent = dump.indent.get();
+        let mut buf = [0u8; 20];
+
+        $f.write_str(dump.label)?;
+        $f.write_str(" (")?;
+        $f.write_str(decimal(dump.bytes.len(), &mut buf))?;
+        $f.write_str(if dump.bytes.len() == 1 {
+            " byte)"
+        } else {
+            " bytes)"
+        })?;
+
+        for (line, chunk) in shown.chunks(16).enumerate() {
+            $f.write_str("\n")?;
+            for _ in 0..indent {
+                $f.write_str(" ")?;
+            }
+            let offset = line * 16;
+            for (i, digit) in buf[..8].iter_mut().enumerate() {
+                *digit = DIGITS[(offset >> (28 - 4 * i)) & 0xf];
+            }
+            $f.write_str(core::str::from_utf8(&buf[..8]).unwrap_or_default())?;
+            $f.write_str("  ")?;
+            for i in 0..16 {
+                match chunk.get(i) {
+                    Some(byte) => {
+                        buf[0] = DIGITS[usize::from(byte >> 4)];
+                        buf[1] = DIGITS[usize::from(byte & 0xf)];
+                        buf[2] = b' ';
+                        $f.write_str(core::str::from_utf8(&buf[..3]).unwrap_or_default())?;
+                    }
+                    None => $f.write_str("   ")?,
+                }
+                if i == 7 {
+                    $f.write_str(" ")?;
                 }
             }
+            $f.write_str(" |")?;
+            for &byte in chunk {
+                buf[0] = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte
+                } else {
+                    b'.'
+                };
+                $f.write_str(core::str::from_utf8(&buf[..1]).unwrap_or_default())?;
+            }
+            $f.write_str("|")?;
         }
-        #[cfg(feature = "std")]
-        #[cfg(feature = "ufmt")]
-        pub fn try_get<O>(
-            &mut self, // Takes reference
-            tryresult: Result<O, Box<dyn core::error::Error>>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            let mut new_self = self.clone();
-            match tryresult {
-                Ok(x) => (x, new_self),
-                Err(err) => {
-                    new_self.log(StatusLevel::Warning, UDebugStr(&err.to_string()));
-                    redirectfn(new_self);
-                    std::process::exit(1);
-                }
+
+        if dump.bytes.len() > shown.len() {
+            $f.write_str("\n")?;
+            for _ in 0..indent {
+                $f.write_str(" ")?;
             }
+            $f.write_str("… (")?;
+            $f.write_str(decimal(dump.bytes.len() - shown.len(), &mut buf))?;
+            $f.write_str(" more bytes)")?;
         }
+        Ok(())
+    }};
+}
 
-        #[cfg(not(feature = "std"))]
-        pub fn try_get<O, E: $error_bound>(
-            &mut self, // Takes reference
-            tryresult: Result<O, E>,
-            redirectfn: fn(Self) -> (),
-        ) -> (O, Self) {
-            let mut new_self = self.clone();
-            match tryresult {
-                Ok(x) => (x, new_self),
-                Err(err) => {
-                    new_self.log(StatusLevel::Warning, err);
-                    redirectfn(new_self);
-                    loop {}
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_hex_dump!(f, self)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl uDebug for HexDump<'_> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        write_hex_dump!(f, self)
+    }
+}
+
+/// Passes writes on to `W` and counts the characters, to measure the line
+/// prefix for [`HexDump`].
+struct CharCount<W>(W, usize);
+
+impl<W: core::fmt::Write> core::fmt::Write for CharCount<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.1 += s.chars().count();
+        self.0.write_str(s)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl uWrite for CharCount<()> {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.1 += s.chars().count();
+        Ok(())
+    }
+}
+
+/// Writes the time and stores the width of `I&:<time> ` in `indent`, so the
+/// [`HexDump`] later in the same line lines up under the message.
+struct MeasuredTime<'a> {
+    time: &'a dyn Display,
+    indent: &'a core::cell::Cell<usize>,
+}
+
+impl Display for MeasuredTime<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut counted = CharCount(f, 0);
+        core::fmt::write(&mut counted, format_args!("{}", self.time))?;
+        self.indent.set(counted.1 + 4);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(all(feature = "ufmt", feature = "alloc"))]
+use alloc::string::ToString;
+
+/// Output destination for [`ULogger`] and [`MultiULogger`].
+///
+/// ```
+/// use cand::{StatusLevel, ULogger, UStorageProvider, VecStorage};
+///
+/// // Keeps `Warning` and worse, e.g. for a separate alarm channel.
+/// struct Alarms(VecStorage);
+///
+/// impl UStorageProvider for Alarms {
+///     fn write_data(&mut self, d: impl ufmt::uDebug, level: &StatusLevel) {
+///         if *level >= StatusLevel::Warning {
+///             self.0.write_data(d, level);
+///         }
+///     }
+/// }
+///
+/// let mut logger = ULogger((), Alarms(VecStorage::new()));
+/// logger.log_info("boot");
+/// logger.log_warn("low battery");
+/// assert!(!logger.1.0.as_str().contains("boot"));
+/// assert!(logger.1.0.as_str().contains("low battery"));
+/// ```
+#[cfg(feature = "ufmt")]
+pub trait UStorageProvider {
+    /// Writes one piece of a message; every piece of the message gets the same
+    /// `level`, so storages can filter or route by severity.
+    fn write_data(&mut self, d: impl uDebug, level: &StatusLevel);
+
+    /// Receives every message logged through [`ULogger`] and [`MultiULogger`],
+    /// without level prefix, time or colors around it.
+    ///
+    /// The default renders the usual line and passes it to
+    /// [`write_data`](UStorageProvider::write_data); backends with their own
+    /// framing, such as `DefmtStorage`, override this.
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use cand::{StatusLevel, ULogger, UStorageProvider, VecStorage};
+    ///
+    /// struct Bare(VecStorage);
+    ///
+    /// impl UStorageProvider for Bare {
+    ///     fn write_data(&mut self, d: impl ufmt::uDebug, level: &StatusLevel) {
+    ///         self.0.write_data(d, level);
+    ///     }
+    ///     fn write_record(&mut self, level: &StatusLevel, _: Duration, message: impl ufmt::uDebug) {
+    ///         self.write_data(message, level);
+    ///     }
+    /// }
+    ///
+    /// let mut logger = ULogger((), Bare(VecStorage::new()));
+    /// logger.logdisp(StatusLevel::Warning, "battery low");
+    /// assert_eq!(logger.1.0.as_str(), "battery low");
+    /// ```
+    fn write_record(&mut self, level: &StatusLevel, elapsed: Duration, message: impl uDebug) {
+        self.write_data(
+            URecord {
+                level: *level,
+                elapsed,
+                payload: message,
+            },
+            level,
+        );
+    }
+}
+
+use core::fmt::Arguments;
+
+/// One log message as handed to [`StorageProvider::write_record`].
+pub struct Record<'a> {
+    pub level: StatusLevel,
+    /// Time since the logger's [`TimeProvider`] was created.
+    pub elapsed: core::time::Duration,
+    /// The message alone, without level prefix, timestamp or colors.
+    pub message: Arguments<'a>,
+    /// Key-value pairs attached with `log_kv`, in call order.
+    pub kv: &'a [(&'a str, &'a dyn Debug)],
+    time: &'a dyn Display,
+    long: bool,
+    /// Only stdout (std) acts on this, by redrawing the progress line in place.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    progress: ProgressLine,
+    /// Set by [`StripAnsi`]: the line is rendered without escape sequences.
+    strip: bool,
+}
+
+/// Where a record stands in a run of `log_progress` calls.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProgressLine {
+    No,
+    Running,
+    Done,
+}
+
+impl<'a> Record<'a> {
+    fn new(
+        level: StatusLevel,
+        elapsed: core::time::Duration,
+        time: &'a dyn Display,
+        message: Arguments<'a>,
+    ) -> Self {
+        Self {
+            level,
+            elapsed,
+            message,
+            kv: &[],
+            time,
+            long: false,
+            progress: ProgressLine::No,
+            strip: false,
+        }
+    }
+}
+
+/// Renders the record as the usual colored log line.
+impl Display for Record<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.strip {
+            let colored = Record {
+                strip: false,
+                ..*self
+            };
+            return core::fmt::write(&mut AnsiStripper::new(f), format_args!("{colored}"));
+        }
+        if self.long {
+            write!(f, "{}", LongLevel(self.level))?;
+        } else {
+            write!(f, "{:?}", self.level)?;
+        }
+        write!(f, "{} {}{}", self.time, self.level.to_color(), self.message)?;
+        for (key, value) in self.kv {
+            write!(f, " {key}={value:?}")?;
+        }
+        writeln!(f, "{}", reset())
+    }
+}
+
+pub trait StorageProvider {
+    /// Write log data directly - single responsibility
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel);
+
+    /// Receives every message logged through [`Logger`] and [`MultiLogger`].
+    ///
+    /// The default renders the usual colored line and passes it to
+    /// [`write_data`](StorageProvider::write_data), so existing storages keep
+    /// working. Structured backends override this to get at the level, time,
+    /// message and the key-value metadata from `log_kv` ([`Record::kv`]) separately.
+    ///
+    /// ```
+    /// use cand::{strip_ansi, Logger, Record, StatusLevel, StorageProvider, VecStorage};
+    ///
+    /// struct Fields(Vec<(String, String)>);
+    ///
+    /// impl StorageProvider for Fields {
+    ///     fn write_data(&mut self, _: core::fmt::Arguments, _: &StatusLevel) {}
+    ///     fn write_record(&mut self, record: &Record) {
+    ///         self.0.extend(record.kv.iter().map(|(key, value)| (key.to_string(), format!("{value:?}"))));
+    ///     }
+    /// }
+    ///
+    /// let meta: [(&str, &dyn core::fmt::Debug); 2] = [("host", &"db.local"), ("port", &5432)];
+    /// let mut plain = Logger((), VecStorage::new());
+    /// plain.log_kv(StatusLevel::Error, "connection failed", &meta);
+    /// assert_eq!(strip_ansi(plain.1.as_str()), "E&: connection failed host=\"db.local\" port=5432\n");
+    ///
+    /// let mut fields = Logger((), Fields(Vec::new()));
+    /// fields.log_kv(StatusLevel::Error, "connection failed", &meta);
+    /// let expected = [("host", "\"db.local\""), ("port", "5432")];
+    /// assert!(fields.1.0.iter().map(|(k, v)| (k.as_str(), v.as_str())).eq(expected));
+    /// ```
+    fn write_record(&mut self, record: &Record) {
+        self.write_data(format_args!("{record}"), &record.level);
+    }
+
+    /// Whether a message at `level` would be written at all.
+    ///
+    /// Loggers check this before formatting, so returning `false` skips the
+    /// formatting cost entirely.
+    fn enabled(&self, _level: &StatusLevel) -> bool {
+        true
+    }
+
+    /// Pushes out anything the storage holds back; the default does nothing.
+    fn flush(&mut self) {}
+}
+
+/// Whether stdout ends in a `log_progress` bar still waiting for its newline.
+#[cfg(feature = "std")]
+static PROGRESS_ON_STDOUT: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+/// Prints to stdout. On a terminal, a `log_progress` bar is redrawn in place
+/// and gets its newline once it is done or something else is printed.
+#[cfg(feature = "std")]
+impl StorageProvider for () {
+    fn write_data(&mut self, args: Arguments<'_>, _debuglevel: &StatusLevel) {
+        if PROGRESS_ON_STDOUT.swap(false, core::sync::atomic::Ordering::Relaxed) {
+            println!();
+        }
+        print!("{args}")
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        use core::sync::atomic::Ordering::Relaxed;
+        if record.progress == ProgressLine::No
+            || !std::io::IsTerminal::is_terminal(&std::io::stdout())
+        {
+            return StorageProvider::write_data(self, format_args!("{record}"), &record.level);
+        }
+        // Back to the start of the line and clear it, then draw the bar.
+        let line = record.to_string();
+        if record.progress == ProgressLine::Running {
+            print!("\r\x1b[2K{}", line.trim_end_matches('\n'));
+        } else {
+            print!("\r\x1b[2K{line}");
+        }
+        PROGRESS_ON_STDOUT.store(record.progress == ProgressLine::Running, Relaxed);
+    }
+
+    fn flush(&mut self) {
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+}
+
+/// Discards everything; [`enabled`](StorageProvider::enabled) is always `false`,
+/// so nothing is formatted either. Used by [`Logger::null`] for tests and benchmarks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullStorage;
+
+impl StorageProvider for NullStorage {
+    fn write_data(&mut self, _args: Arguments, _debuglevel: &StatusLevel) {}
+
+    fn enabled(&self, _level: &StatusLevel) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl UStorageProvider for NullStorage {
+    fn write_data(&mut self, _d: impl uDebug, _level: &StatusLevel) {}
+}
+
+/// `fmt::Write` adapter that removes ANSI escape sequences on the way through.
+///
+/// The escape state survives across `write_str` calls, so sequences split
+/// between format pieces are still removed.
+struct AnsiStripper<W: core::fmt::Write> {
+    inner: W,
+    state: EscapeState,
+}
+
+#[derive(Clone, Copy)]
+enum EscapeState {
+    Text,
+    Escape,
+    Csi,
+}
+
+impl<W: core::fmt::Write> AnsiStripper<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: EscapeState::Text,
+        }
+    }
+}
+
+impl<W: core::fmt::Write> core::fmt::Write for AnsiStripper<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            match self.state {
+                EscapeState::Text => {
+                    if c == '\x1b' {
+                        self.inner.write_str(&s[start..i])?;
+                        self.state = EscapeState::Escape;
+                    }
+                }
+                EscapeState::Escape => {
+                    // `ESC [` opens a CSI sequence, anything else is a two-byte escape.
+                    self.state = if c == '[' {
+                        EscapeState::Csi
+                    } else {
+                        EscapeState::Text
+                    };
+                    start = i + c.len_utf8();
+                }
+                EscapeState::Csi => {
+                    // A new ESC abandons an unterminated sequence.
+                    if c == '\x1b' {
+                        self.state = EscapeState::Escape;
+                    } else if ('@'..='~').contains(&c) {
+                        self.state = EscapeState::Text;
+                    }
+                    start = i + c.len_utf8();
                 }
             }
         }
-    };
-}
+        if let EscapeState::Text = self.state {
+            self.inner.write_str(&s[start..])?;
+        }
+        Ok(())
+    }
+}
+
+/// Bridges `fmt::Write` onto an `io::Write`, keeping the `io::Error` that
+/// `fmt::Error` can't carry.
+#[cfg(feature = "std")]
+struct IoFmt<W: std::io::Write>(W, Option<std::io::Error>);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoFmt<W> {
+    fn new(inner: W) -> Self {
+        IoFmt(inner, None)
+    }
+
+    fn take_error(&mut self) -> std::io::Error {
+        self.1
+            .take()
+            .unwrap_or_else(|| std::io::Error::other("formatter error"))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> core::fmt::Write for IoFmt<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.write_all(s.as_bytes()).map_err(|err| {
+            self.1 = Some(err);
+            core::fmt::Error
+        })
+    }
+}
+
+/// Sends each formatted line, colors included, to another thread, e.g. a disk writer.
+///
+/// Uses `try_send`, so a full or disconnected channel drops the line instead of
+/// blocking the caller.
+#[cfg(feature = "std")]
+impl StorageProvider for std::sync::mpsc::SyncSender<String> {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        let _ = self.try_send(args.to_string());
+    }
+}
+
+/// Writes log lines to any [`std::io::Write`], e.g. `IoWriter(std::io::stderr())`.
+///
+/// Write errors are ignored. For stdout and stderr the handle stays locked for
+/// the whole line, so lines from different threads never interleave.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct IoWriter<W: std::io::Write>(pub W);
+
+/// Alternative name for [`IoWriter`], usable as a constructor too:
+/// `WriteStorageProvider(File::create("log.txt")?)`.
+#[cfg(feature = "std")]
+pub use IoWriter as WriteStorageProvider;
+
+#[cfg(feature = "std")]
+impl IoWriter<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        IoWriter(std::io::stdout())
+    }
+}
+
+#[cfg(feature = "std")]
+impl IoWriter<std::io::Stderr> {
+    pub fn stderr() -> Self {
+        IoWriter(std::io::stderr())
+    }
+}
+
+#[cfg(feature = "std")]
+impl IoWriter<std::fs::File> {
+    /// Opens `path` for appending, creating it if it does not exist. Colors are
+    /// kept; use [`FileStorage`] for plain-text files.
+    pub fn file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map(IoWriter)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> StorageProvider for IoWriter<W> {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        let _ = self.0.write_fmt(args);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.0.flush();
+    }
+}
+
+/// Writes log lines through a [`std::io::BufWriter`], flushing on `Error` and
+/// worse, on [`flush`](StorageProvider::flush) and on drop.
+///
+/// A failed flush on drop is reported as a `Critical` line straight to stderr,
+/// since the storage itself can no longer be written.
+///
+/// ```
+/// use cand::{BufWriterStorageProvider, Logger};
+///
+/// let mut logger = Logger((), BufWriterStorageProvider::new(Vec::new()));
+/// logger.log_info("batched");
+/// assert!(logger.1.inner().get_ref().is_empty());
+/// logger.log_critical("flushed right away");
+/// assert!(!logger.1.inner().get_ref().is_empty());
+/// ```
+#[cfg(feature = "std")]
+pub struct BufWriterStorageProvider<W: std::io::Write>(std::io::BufWriter<W>);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> BufWriterStorageProvider<W> {
+    pub fn new(writer: W) -> Self {
+        BufWriterStorageProvider(std::io::BufWriter::new(writer))
+    }
+
+    pub fn with_capacity(capacity: usize, writer: W) -> Self {
+        BufWriterStorageProvider(std::io::BufWriter::with_capacity(capacity, writer))
+    }
+
+    pub fn inner(&self) -> &std::io::BufWriter<W> {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> StorageProvider for BufWriterStorageProvider<W> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let _ = std::io::Write::write_fmt(&mut self.0, args);
+        if *debuglevel >= StatusLevel::Error {
+            let _ = std::io::Write::flush(&mut self.0);
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = std::io::Write::flush(&mut self.0);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Drop for BufWriterStorageProvider<W> {
+    fn drop(&mut self) {
+        if let Err(err) = std::io::Write::flush(&mut self.0) {
+            let message = format_args!("buffered log lines lost: {err}");
+            let record = Record::new(
+                StatusLevel::Critical,
+                core::time::Duration::ZERO,
+                &"",
+                message,
+            );
+            let _ = std::io::Write::write_fmt(&mut std::io::stderr(), format_args!("{record}"));
+        }
+    }
+}
+
+/// Writes one JSON object per message:
+/// `{"level":"warning","elapsed_ms":1234,"message":"..."}`.
+///
+/// `elapsed_ms` is the logger's elapsed time in milliseconds; color codes are removed
+/// from the message and it is escaped as a JSON string. Key-value pairs from
+/// `log_kv` follow as fields in order: numbers and booleans as JSON values,
+/// strings and everything else as JSON strings.
+///
+/// ```
+/// use cand::{JsonStorage, Logger, StatusLevel};
+///
+/// let mut logger = Logger((), JsonStorage(Vec::new()));
+/// let kv: [(&str, &dyn core::fmt::Debug); 5] =
+///     [("bus", &0u8), ("load", &-0.5f32), ("ok", &false), ("dev", &"eth\"0\""), ("id", &Some(7))];
+/// logger.log_kv(StatusLevel::Warning, "i2c \"timeout\"\n", &kv);
+///
+/// let line = String::from_utf8(logger.1.0)?;
+/// assert!(line.trim_end().ends_with(r#""bus":0,"load":-0.5,"ok":false,"dev":"eth\"0\"","id":"Some(7)"}"#));
+/// let json: serde_json::Value = serde_json::from_str(&line)?;
+/// assert_eq!(json["level"].as_str().unwrap().parse(), Ok(StatusLevel::Warning));
+/// #[cfg(feature = "serde")]
+/// assert_eq!(serde_json::from_value::<StatusLevel>(json["level"].clone())?, StatusLevel::Warning);
+/// assert!(json["elapsed_ms"].is_u64());
+/// assert_eq!(json["message"], "i2c \"timeout\"\n");
+/// assert_eq!(json["bus"], 0);
+/// assert_eq!(json["load"], -0.5);
+/// assert_eq!(json["ok"], false);
+/// assert_eq!(json["dev"], "eth\"0\"");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Backslashes and control characters round-trip, and every message is one line:
+///
+/// ```
+/// use cand::{set_color_mode, ColorMode, JsonStorage, Logger, StatusLevel};
+///
+/// set_color_mode(ColorMode::Always);
+/// let mut logger = Logger((), JsonStorage(Vec::new()));
+/// let messages = ["C:\\logs\\app.log", "tab\there\u{1}\r\n", "\x1b[31mred\x1b[0m"];
+/// for message in messages {
+///     logger.logdisp(StatusLevel::Error, message);
+/// }
+/// let out = String::from_utf8(logger.1.0)?;
+/// let parsed: Vec<serde_json::Value> = out.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+/// let parsed: Vec<&str> = parsed.iter().map(|json| json["message"].as_str().unwrap()).collect();
+/// assert_eq!(parsed, ["C:\\logs\\app.log", "tab\there\u{1}\r\n", "red"]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct JsonStorage<W: std::io::Write>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> StorageProvider for JsonStorage<W> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let _ = writeln!(
+            self.0,
+            "{{\"level\":\"{}\",\"message\":\"{}\"}}",
+            debuglevel.json_name(),
+            JsonString(args)
+        );
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        let _ = write!(
+            self.0,
+            "{{\"level\":\"{}\",\"elapsed_ms\":{},\"message\":\"{}\"",
+            record.level.json_name(),
+            record.elapsed.as_millis(),
+            JsonString(record.message)
+        );
+        for (key, value) in record.kv {
+            let _ = write!(
+                self.0,
+                ",\"{}\":{}",
+                JsonString(format_args!("{key}")),
+                JsonValue(*value)
+            );
+        }
+        let _ = writeln!(self.0, "}}");
+    }
+
+    fn flush(&mut self) {
+        let _ = self.0.flush();
+    }
+}
+
+#[cfg(feature = "std")]
+impl StatusLevel {
+    fn json_name(self) -> &'static str {
+        match self {
+            StatusLevel::Ok => "ok",
+            StatusLevel::Info => "info",
+            StatusLevel::Error => "error",
+            StatusLevel::Warning => "warning",
+            StatusLevel::Critical => "critical",
+        }
+    }
+}
+
+/// Displays the arguments as the inside of a JSON string, colors removed.
+#[cfg(feature = "std")]
+struct JsonString<'a>(Arguments<'a>);
+
+#[cfg(feature = "std")]
+impl Display for JsonString<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::write(&mut AnsiStripper::new(JsonEscaper(f)), self.0)
+    }
+}
+
+/// Displays a key-value value as a JSON scalar: numbers and booleans as they
+/// are, strings and chars unquoted from their `Debug` form, anything else as
+/// its `Debug` text in a string.
+#[cfg(feature = "std")]
+struct JsonValue<'a>(&'a dyn Debug);
+
+#[cfg(feature = "std")]
+impl Display for JsonValue<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let text = format!("{:?}", self.0);
+        if text == "true" || text == "false" || is_json_number(&text) {
+            return f.write_str(&text);
+        }
+        f.write_str("\"")?;
+        let mut out = AnsiStripper::new(JsonEscaper(&mut *f));
+        let quoted = ['"', '\''].into_iter().find_map(|quote| {
+            text.strip_prefix(quote)
+                .and_then(|inner| inner.strip_suffix(quote))
+        });
+        match quoted {
+            Some(inner) => write_unescaped(&mut out, inner)?,
+            None => core::fmt::Write::write_str(&mut out, &text)?,
+        }
+        f.write_str("\"")
+    }
+}
+
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`, which also covers Rust's
+/// `Debug` output for integers and finite floats.
+#[cfg(feature = "std")]
+fn is_json_number(text: &str) -> bool {
+    fn digits(s: &str) -> (usize, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (end, &s[end..])
+    }
+    let s = text.strip_prefix('-').unwrap_or(text);
+    let (n, mut s) = digits(s);
+    if n == 0 || (n > 1 && text.trim_start_matches('-').starts_with('0')) {
+        return false;
+    }
+    if let Some(rest) = s.strip_prefix('.') {
+        let (n, rest) = digits(rest);
+        if n == 0 {
+            return false;
+        }
+        s = rest;
+    }
+    if let Some(rest) = s.strip_prefix(['e', 'E']) {
+        let (n, rest) = digits(rest.strip_prefix(['+', '-']).unwrap_or(rest));
+        if n == 0 {
+            return false;
+        }
+        s = rest;
+    }
+    s.is_empty()
+}
+
+/// Undoes the escapes of `str::escape_debug`, e.g. `\"` and `\u{1b}`.
+#[cfg(feature = "std")]
+fn write_unescaped(out: &mut impl core::fmt::Write, escaped: &str) -> core::fmt::Result {
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.write_char(c)?;
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.write_char('\n')?,
+            Some('r') => out.write_char('\r')?,
+            Some('t') => out.write_char('\t')?,
+            Some('0') => out.write_char('\0')?,
+            Some('u') => {
+                let rest = chars.as_str();
+                let code = rest
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .and_then(|(hex, tail)| {
+                        let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+                        Some((c, tail))
+                    });
+                match code {
+                    Some((c, tail)) => {
+                        out.write_char(c)?;
+                        chars = tail.chars();
+                    }
+                    None => out.write_str("\\u")?,
+                }
+            }
+            Some(other) => out.write_char(other)?,
+            None => out.write_char('\\')?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+struct JsonEscaper<W: core::fmt::Write>(W);
+
+#[cfg(feature = "std")]
+impl<W: core::fmt::Write> core::fmt::Write for JsonEscaper<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            if c != '"' && c != '\\' && c >= ' ' {
+                continue;
+            }
+            self.0.write_str(&s[start..i])?;
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                _ => write!(self.0, "\\u{:04x}", c as u32)?,
+            }
+            start = i + c.len_utf8();
+        }
+        self.0.write_str(&s[start..])
+    }
+}
+
+/// Writes RFC 5424 syslog lines:
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID - - MESSAGE`.
+///
+/// `PRI` is `facility * 8` plus [`StatusLevel::syslog_severity`]; the facility
+/// defaults to 1 (user). The timestamp is the UTC wall-clock time, the hostname
+/// is `-` unless set with [`with_hostname`](Self::with_hostname), and colors are
+/// removed from the message. Each line reaches the writer in one `write_all`,
+/// so a datagram socket wrapper sends one message per packet.
+///
+/// ```
+/// use cand::{Logger, StatusLevel, SyslogStorageProvider};
+///
+/// let mut logger = Logger((), SyslogStorageProvider::new(Vec::new(), "sensord").with_hostname("pi"));
+/// logger.logdisp(StatusLevel::Warning, "disk almost full");
+/// let line = String::from_utf8(logger.1.into_inner())?;
+/// assert!(line.starts_with("<12>1 "));
+/// assert!(line.ends_with(&format!(" pi sensord {} - - disk almost full\n", std::process::id())));
+/// # Ok::<(), std::string::FromUtf8Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub struct SyslogStorageProvider<W: std::io::Write> {
+    writer: W,
+    app_name: String,
+    hostname: String,
+    facility: u8,
+    line: String,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> SyslogStorageProvider<W> {
+    pub fn new(writer: W, app_name: impl Into<String>) -> Self {
+        SyslogStorageProvider {
+            writer,
+            app_name: app_name.into(),
+            hostname: String::from("-"),
+            facility: 1,
+            line: String::new(),
+        }
+    }
+
+    /// Sets the `HOSTNAME` field, `-` by default.
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    /// Sets the syslog facility (0..=23), e.g. 16 for `local0`. Larger values are clamped.
+    pub fn with_facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_line(&mut self, level: StatusLevel, message: Arguments, kv: &[(&str, &dyn Debug)]) {
+        use core::fmt::Write as _;
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.line.clear();
+        let _ = write!(
+            self.line,
+            "<{}>1 {} {} {} {} - - ",
+            self.facility * 8 + level.syslog_severity(),
+            Rfc3339(since_epoch),
+            self.hostname,
+            self.app_name,
+            std::process::id()
+        );
+        let _ = core::fmt::Write::write_fmt(&mut AnsiStripper::new(&mut self.line), message);
+        for (key, value) in kv {
+            let _ = write!(self.line, " {key}={value:?}");
+        }
+        if !self.line.ends_with('\n') {
+            self.line.push('\n');
+        }
+        let _ = self.writer.write_all(self.line.as_bytes());
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> StorageProvider for SyslogStorageProvider<W> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.write_line(*debuglevel, args, &[]);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        self.write_line(record.level, record.message, record.kv);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Sends log lines to a collector over TCP, reconnecting after failures.
+///
+/// A failed write marks the storage disconnected; later log calls retry the
+/// connection with exponential backoff (100ms doubling up to 30s by default),
+/// one address and at most one attempt per interval, so a log call blocks for
+/// at most one connect or write timeout. The address is resolved again once
+/// every address has been tried. Meanwhile up to 256 lines are kept, see
+/// [`with_backlog`](Self::with_backlog), and further lines are counted as
+/// [`dropped`](Self::dropped). After reconnecting the backlog is sent first,
+/// starting where a failed write stopped, followed by a `Warning` with the
+/// number of lines lost.
+///
+/// ```
+/// use std::io::{BufRead, BufReader};
+/// use std::net::TcpListener;
+/// use std::time::Duration;
+/// use cand::{Logger, StatusLevel, TcpStorage};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// let storage = TcpStorage::connect(addr).with_backoff(Duration::from_millis(1), Duration::from_millis(10));
+/// let mut logger = Logger((), storage);
+///
+/// let (conn, _) = listener.accept()?;
+/// logger.logdisp(StatusLevel::Info, "before");
+/// let mut line = String::new();
+/// BufReader::new(&conn).read_line(&mut line)?;
+/// assert!(line.contains("before"));
+///
+/// // The collector goes away; writes start failing.
+/// drop((conn, listener));
+/// while logger.1.is_connected() {
+///     logger.logdisp(StatusLevel::Info, "while down");
+///     std::thread::sleep(Duration::from_millis(1));
+/// }
+///
+/// // It comes back on the same port; the next log calls reconnect.
+/// let listener = TcpListener::bind(addr)?;
+/// while !logger.1.is_connected() {
+///     std::thread::sleep(Duration::from_millis(10));
+///     logger.logdisp(StatusLevel::Info, "after");
+/// }
+/// let (conn, _) = listener.accept()?;
+/// conn.set_read_timeout(Some(Duration::from_secs(5)))?;
+/// let mut lines = BufReader::new(conn).lines();
+/// assert!(lines.any(|line| line.unwrap().contains("after")));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub struct TcpStorage {
+    resolve: Box<dyn FnMut() -> Vec<std::net::SocketAddr> + Send>,
+    addrs: Vec<std::net::SocketAddr>,
+    next_addr: usize,
+    stream: Option<std::net::TcpStream>,
+    backlog: std::collections::VecDeque<String>,
+    /// Bytes of the first backlog line that already went out.
+    sent: usize,
+    capacity: usize,
+    dropped: u64,
+    reported: u64,
+    min_backoff: core::time::Duration,
+    max_backoff: core::time::Duration,
+    backoff: core::time::Duration,
+    retry_at: Instant,
+}
+
+#[cfg(feature = "std")]
+impl TcpStorage {
+    /// How long a single connection attempt may block a log call.
+    const CONNECT_TIMEOUT: core::time::Duration = core::time::Duration::from_millis(200);
+    /// How long a single write may block a log call.
+    const WRITE_TIMEOUT: core::time::Duration = core::time::Duration::from_millis(200);
+
+    /// Connects to `addr`; if that fails, the storage starts out disconnected
+    /// and retries on later log calls.
+    pub fn connect(addr: impl std::net::ToSocketAddrs + Send + 'static) -> Self {
+        let min_backoff = core::time::Duration::from_millis(100);
+        let mut storage = Self {
+            resolve: Box::new(move || {
+                addr.to_socket_addrs()
+                    .map(Iterator::collect)
+                    .unwrap_or_default()
+            }),
+            addrs: Vec::new(),
+            next_addr: 0,
+            stream: None,
+            backlog: std::collections::VecDeque::new(),
+            sent: 0,
+            capacity: 256,
+            dropped: 0,
+            reported: 0,
+            min_backoff,
+            max_backoff: core::time::Duration::from_secs(30),
+            backoff: min_backoff,
+            retry_at: Instant::now(),
+        };
+        storage.reconnect();
+        storage
+    }
+
+    /// Keeps at most `lines` lines while disconnected.
+    pub fn with_backlog(mut self, lines: usize) -> Self {
+        self.capacity = lines;
+        self
+    }
+
+    /// Waits `min` after the first failed attempt, doubling up to `max`.
+    pub fn with_backoff(mut self, min: core::time::Duration, max: core::time::Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self.backoff = min;
+        self.retry_at = Instant::now();
+        self
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Lines dropped because the backlog was full, since creation.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Tries the next address. The backoff only goes back down once a write
+    /// succeeds, so a collector that accepts and then drops connections is not
+    /// retried in a tight loop.
+    fn reconnect(&mut self) {
+        if self.next_addr >= self.addrs.len() {
+            let addrs = (self.resolve)();
+            if !addrs.is_empty() {
+                self.addrs = addrs;
+            }
+            self.next_addr = 0;
+        }
+        self.retry_at = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(self.max_backoff);
+        let Some(addr) = self.addrs.get(self.next_addr) else {
+            return;
+        };
+        self.next_addr += 1;
+        if let Ok(stream) = std::net::TcpStream::connect_timeout(addr, Self::CONNECT_TIMEOUT) {
+            let _ = stream.set_nodelay(true);
+            let _ = stream.set_write_timeout(Some(Self::WRITE_TIMEOUT));
+            self.stream = Some(stream);
+            // Resolve again before the next attempt after this connection fails.
+            self.next_addr = self.addrs.len();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+        self.retry_at = Instant::now() + self.backoff;
+    }
+
+    /// Writes `bytes`, or returns how many of them went out before the write failed.
+    fn send(stream: &mut std::net::TcpStream, bytes: &[u8]) -> Result<(), usize> {
+        let mut written = 0;
+        while written < bytes.len() {
+            match std::io::Write::write(stream, &bytes[written..]) {
+                Ok(0) => return Err(written),
+                Ok(n) => written += n,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(_) => return Err(written),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the backlog, then the dropped-lines notice; stops at the first failure.
+    fn send_backlog(&mut self) {
+        let Some(stream) = self.stream.as_mut() else {
+            return;
+        };
+        while let Some(line) = self.backlog.front() {
+            if let Err(written) = Self::send(stream, &line.as_bytes()[self.sent..]) {
+                self.sent += written;
+                return self.disconnect();
+            }
+            self.sent = 0;
+            self.backlog.pop_front();
+            self.backoff = self.min_backoff;
+        }
+        let dropped = self.dropped - self.reported;
+        if dropped > 0 {
+            let notice = format_args!("{dropped} log messages dropped while disconnected");
+            let record = Record::new(
+                StatusLevel::Warning,
+                core::time::Duration::ZERO,
+                &"",
+                notice,
+            );
+            // Queued like any other line, so a partial write resumes where it stopped.
+            self.backlog.push_back(record.to_string());
+            self.reported = self.dropped;
+            self.send_backlog();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageProvider for TcpStorage {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        if self.stream.is_none() && Instant::now() >= self.retry_at {
+            self.reconnect();
+        }
+        self.send_backlog();
+        if self.stream.is_some() || self.backlog.len() < self.capacity {
+            // With a connection the backlog is empty here, so this line goes out next.
+            self.backlog.push_back(args.to_string());
+            self.send_backlog();
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    fn flush(&mut self) {
+        self.send_backlog();
+        if let Some(stream) = self.stream.as_mut()
+            && std::io::Write::flush(stream).is_err()
+        {
+            self.disconnect();
+        }
+    }
+}
+
+/// Appends log lines to a file with the color codes stripped.
+///
+/// Output is buffered and flushed after `Error` and `Critical` messages, on
+/// [`flush`](FileStorage::flush) and when the storage is dropped.
+///
+/// ```
+/// use cand::{set_color_mode, ColorMode, FileStorage, Logger, StatusLevel};
+///
+/// set_color_mode(ColorMode::Always);
+/// let path = std::env::temp_dir().join(format!("cand-file-{}.log", std::process::id()));
+/// let mut logger = Logger((), FileStorage::new(&path)?);
+/// logger.logdisp(StatusLevel::Info, "one");
+/// logger.logdisp(StatusLevel::Warning, "two");
+/// drop(logger);
+/// // Reopening appends instead of truncating.
+/// let mut logger = Logger((), FileStorage::new(&path)?);
+/// logger.logdisp(StatusLevel::Ok, "three");
+/// drop(logger);
+/// assert_eq!(std::fs::read_to_string(&path)?, "I&: one\nW&: two\nO&: three\n");
+/// std::fs::remove_file(&path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub struct FileStorage {
+    file: std::io::BufWriter<std::fs::File>,
+    echo: bool,
+}
+
+#[cfg(feature = "std")]
+impl FileStorage {
+    /// Opens `path` for appending, creating it if it does not exist.
+    pub fn new(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: std::io::BufWriter::new(file),
+            echo: false,
+        })
+    }
+
+    /// Also prints every message, colors included, to stdout.
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        std::io::Write::flush(&mut self.file)
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageProvider for FileStorage {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        if self.echo {
+            print!("{args}");
+        }
+        let _ =
+            core::fmt::Write::write_fmt(&mut AnsiStripper::new(IoFmt::new(&mut self.file)), args);
+        if *debuglevel >= StatusLevel::Error {
+            let _ = self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = FileStorage::flush(self);
+    }
+}
+
+/// File storage that rotates `app.log` → `app.log.1` → `app.log.2` … once the
+/// current file would grow past `max_bytes`.
+///
+/// Rotation only happens between whole lines and at most `keep` old files are
+/// retained. Color codes are stripped like in [`FileStorage`].
+///
+/// ```
+/// use cand::{Logger, RotatingFileStorage, StatusLevel};
+///
+/// let dir = std::env::temp_dir().join(format!("cand-rotate-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let path = dir.join("app.log");
+/// let mut logger = Logger((), RotatingFileStorage::new(&path, 64, 2)?);
+/// for n in 0..10 {
+///     logger.logdisp(StatusLevel::Info, format_args!("sensor sweep {n} done"));
+/// }
+/// drop(logger);
+/// assert!(std::fs::metadata(&path)?.len() <= 64);
+/// assert!(dir.join("app.log.2").exists());
+/// assert!(!dir.join("app.log.3").exists());
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Each file holds whole lines, newest in `app.log`:
+///
+/// ```
+/// use cand::{Logger, RotatingFileStorage, StatusLevel};
+///
+/// let dir = std::env::temp_dir().join(format!("cand-rotate-lines-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let path = dir.join("app.log");
+/// // Each line is 11 bytes, so two fit under the limit.
+/// let mut logger = Logger((), RotatingFileStorage::new(&path, 25, 2)?);
+/// for n in 0..10 {
+///     logger.logdisp(StatusLevel::Info, format_args!("line {n}"));
+/// }
+/// drop(logger);
+/// let read = |name: &str| std::fs::read_to_string(dir.join(name));
+/// assert_eq!(read("app.log")?, "I&: line 8\nI&: line 9\n");
+/// assert_eq!(read("app.log.1")?, "I&: line 6\nI&: line 7\n");
+/// assert_eq!(read("app.log.2")?, "I&: line 4\nI&: line 5\n");
+/// assert!(!dir.join("app.log.3").exists());
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub struct RotatingFileStorage {
+    path: std::path::PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: std::io::BufWriter<std::fs::File>,
+    size: u64,
+    line: String,
+}
+
+#[cfg(feature = "std")]
+impl RotatingFileStorage {
+    /// Opens `path` for appending, creating it if it does not exist.
+    pub fn new(
+        path: impl AsRef<std::path::Path>,
+        max_bytes: u64,
+        keep: usize,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = Self::open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            keep,
+            file: std::io::BufWriter::new(file),
+            size,
+            line: String::new(),
+        })
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        std::io::Write::flush(&mut self.file)
+    }
+
+    fn open(path: &std::path::Path) -> std::io::Result<std::fs::File> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+    }
+
+    fn rotated(&self, index: usize) -> std::path::PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        name.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.flush()?;
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated(self.keep));
+            for index in (1..self.keep).rev() {
+                let _ = std::fs::rename(self.rotated(index), self.rotated(index + 1));
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = std::io::BufWriter::new(Self::open(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageProvider for RotatingFileStorage {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.line.clear();
+        let _ = core::fmt::Write::write_fmt(&mut AnsiStripper::new(&mut self.line), args);
+        let len = self.line.len() as u64;
+        if self.size > 0 && self.size + len > self.max_bytes && self.rotate().is_err() {
+            return;
+        }
+        if std::io::Write::write_all(&mut self.file, self.line.as_bytes()).is_ok() {
+            self.size += len;
+        }
+        if *debuglevel >= StatusLevel::Error {
+            let _ = self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = RotatingFileStorage::flush(self);
+    }
+}
+
+/// Collects the formatted output in memory, handy for asserting on log output in tests.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Default)]
+pub struct VecStorage(alloc::string::String);
+
+#[cfg(feature = "alloc")]
+impl VecStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl StorageProvider for VecStorage {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        let _ = core::fmt::Write::write_fmt(&mut self.0, args);
+    }
+}
+
+#[cfg(all(feature = "alloc", feature = "ufmt"))]
+impl uWrite for VecStorage {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.0.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "alloc", feature = "ufmt"))]
+impl UStorageProvider for VecStorage {
+    fn write_data(&mut self, d: impl uDebug, _level: &StatusLevel) {
+        let _ = d.fmt(&mut ufmt::Formatter::new(self));
+    }
+}
+
+/// Keeps the most recent log records in a fixed array of `N` bytes, e.g. for a
+/// crash handler to dump. Needs neither `std` nor `alloc`, and `new` is `const`
+/// so it can live in a `static`.
+///
+/// Each `write_data` call is one record, expected to end in a newline like the
+/// loggers' lines. When full, whole records are dropped oldest-first; a single
+/// record larger than the buffer keeps its beginning, cut at a UTF-8 boundary
+/// and terminated with a newline.
+///
+/// ```
+/// use cand::{RingBufferStorage, StatusLevel, StorageProvider};
+///
+/// let mut ring = RingBufferStorage::<20>::new();
+/// let level = StatusLevel::Info;
+/// ring.write_data(format_args!("boot\n"), &level);
+/// ring.write_data(format_args!("link up\n"), &level);
+/// ring.write_data(format_args!("sensor ok\n"), &level); // wraps, drops "boot"
+/// assert!(ring.iter_records().eq(["link up", "sensor ok"]));
+///
+/// ring.write_data(format_args!("this line is far too long\n"), &level);
+/// assert!(ring.iter_records().eq(["this line is far to"]));
+///
+/// ring.write_data(format_args!("naïve café☕☕☕\n"), &level); // cut inside a ☕
+/// assert!(ring.iter_records().eq(["naïve café☕☕"]));
+///
+/// // A plain `write_str` after a cut record starts a record of its own.
+/// core::fmt::Write::write_str(&mut ring, "0123456\n")?;
+/// assert!(ring.iter_records().eq(["0123456"]));
+/// # Ok::<(), core::fmt::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RingBufferStorage<const N: usize> {
+    buf: [u8; N],
+    start: usize,
+    len: usize,
+    /// Offset from `start` where the record being written begins.
+    record: usize,
+    in_record: bool,
+    truncated: bool,
+}
+
+impl<const N: usize> RingBufferStorage<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            start: 0,
+            len: 0,
+            record: 0,
+            in_record: false,
+            truncated: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+        self.record = 0;
+    }
+
+    /// Contents oldest-first, split in two where the buffer wraps around.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        let end = self.start + self.len;
+        if end <= N {
+            (&self.buf[self.start..end], &[])
+        } else {
+            (&self.buf[self.start..], &self.buf[..end - N])
+        }
+    }
+
+    /// Contents oldest-first as one slice; rotates the buffer in place if needed.
+    pub fn as_bytes(&mut self) -> &[u8] {
+        self.buf.rotate_left(self.start);
+        self.start = 0;
+        &self.buf[..self.len]
+    }
+
+    /// The records oldest-first, without their trailing newlines.
+    pub fn iter_records(&mut self) -> impl Iterator<Item = &str> + '_ {
+        self.as_str().split_terminator('\n')
+    }
+
+    /// Writes the contents oldest-first to `w`, e.g. a UART from a panic handler.
+    #[cfg(feature = "ufmt")]
+    pub fn dump_to<W: uWrite + ?Sized>(&mut self, w: &mut W) -> Result<(), W::Error> {
+        w.write_str(self.as_str())
+    }
+
+    fn as_str(&mut self) -> &str {
+        let bytes = self.as_bytes();
+        match core::str::from_utf8(bytes) {
+            Ok(text) => text,
+            // Only reachable through raw writes that were cut mid-character.
+            Err(err) => core::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+        }
+    }
+
+    fn begin_record(&mut self) {
+        self.record = self.len;
+        self.in_record = true;
+        self.truncated = false;
+    }
+
+    fn end_record(&mut self) {
+        if self.truncated {
+            // `write` kept a byte free for this.
+            self.push(b"\n");
+        }
+        self.in_record = false;
+        self.truncated = false;
+    }
+
+    /// Drops whole records oldest-first until `needed` bytes are free or only the
+    /// current record is left.
+    fn make_room(&mut self, needed: usize) {
+        while N - self.len < needed && self.record > 0 {
+            let (first, second) = self.as_slices();
+            let dropped = first
+                .iter()
+                .chain(second)
+                .take(self.record)
+                .position(|&byte| byte == b'\n')
+                .map_or(self.record, |newline| newline + 1);
+            self.start = (self.start + dropped) % N;
+            self.len -= dropped;
+            self.record -= dropped;
+        }
+    }
+
+    fn write(&mut self, s: &str) {
+        if N == 0 || self.truncated || s.is_empty() {
+            return;
+        }
+        let single = !self.in_record;
+        if single {
+            self.begin_record();
+        }
+        self.make_room(s.len());
+        let free = N - self.len;
+        if s.len() <= free {
+            self.push(s.as_bytes());
+        } else if free == 0 {
+            // The record filled the buffer exactly; free its last character
+            // for the newline.
+            while self.len > self.record {
+                self.len -= 1;
+                if self.buf[(self.start + self.len) % N] & 0xc0 != 0x80 {
+                    break;
+                }
+            }
+            self.truncated = true;
+        } else {
+            let mut cut = free - 1;
+            while !s.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            self.push(&s.as_bytes()[..cut]);
+            self.truncated = true;
+        }
+        if single {
+            self.end_record();
+        }
+    }
+
+    /// Appends `bytes`, which must fit in the free space.
+    fn push(&mut self, bytes: &[u8]) {
+        let at = (self.start + self.len) % N;
+        let first = bytes.len().min(N - at);
+        self.buf[at..at + first].copy_from_slice(&bytes[..first]);
+        self.buf[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+        self.len += bytes.len();
+    }
+}
+
+impl<const N: usize> Default for RingBufferStorage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Each `write_str` outside of `write_data` counts as a record of its own.
+impl<const N: usize> core::fmt::Write for RingBufferStorage<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s);
+        Ok(())
+    }
+}
+
+impl<const N: usize> StorageProvider for RingBufferStorage<N> {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        self.begin_record();
+        let _ = core::fmt::Write::write_fmt(self, args);
+        self.end_record();
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<const N: usize> uWrite for RingBufferStorage<N> {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.write(s);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<const N: usize> UStorageProvider for RingBufferStorage<N> {
+    fn write_data(&mut self, d: impl uDebug, _level: &StatusLevel) {
+        self.begin_record();
+        let _ = d.fmt(&mut ufmt::Formatter::new(self));
+        self.end_record();
+    }
+}
+
+/// Formats each record into a stack buffer of `N` bytes and passes the finished
+/// line to `sink`, e.g. a UART DMA or RTT write. Needs neither `std` nor `alloc`.
+///
+/// A line that does not fit is cut at a UTF-8 boundary and ends in `…\n`
+/// instead, so `N` should be at least 4.
+///
+/// ```
+/// use cand::{FixedLineStorage, StatusLevel, StorageProvider};
+///
+/// let mut lines = Vec::new();
+/// let mut storage = FixedLineStorage::<8, _>::new(|line: &str| lines.push(line.to_owned()));
+/// let level = StatusLevel::Info;
+/// storage.write_data(format_args!("1234567\n"), &level); // exactly 8 bytes
+/// storage.write_data(format_args!("123456789\n"), &level);
+/// storage.write_data(format_args!("abcé{}\n", 4242), &level); // cut inside "é"
+/// drop(storage);
+/// assert_eq!(lines, ["1234567\n", "1234…\n", "abc…\n"]);
+/// ```
+pub struct FixedLineStorage<const N: usize, F: FnMut(&str)> {
+    buf: [u8; N],
+    len: usize,
+    truncated: bool,
+    sink: F,
+}
+
+impl<const N: usize, F: FnMut(&str)> FixedLineStorage<N, F> {
+    const MARKER: &'static str = "…\n";
+
+    pub fn new(sink: F) -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            truncated: false,
+            sink,
+        }
+    }
+
+    /// Makes room for [`MARKER`](Self::MARKER) at a character boundary and appends it.
+    fn mark_truncated(&mut self) {
+        let Some(mut cut) = N.checked_sub(Self::MARKER.len()) else {
+            return;
+        };
+        cut = cut.min(self.len);
+        while cut < self.len && self.buf[cut] & 0xc0 == 0x80 {
+            cut -= 1;
+        }
+        self.buf[cut..cut + Self::MARKER.len()].copy_from_slice(Self::MARKER.as_bytes());
+        self.len = cut + Self::MARKER.len();
+    }
+}
+
+impl<const N: usize, F: FnMut(&str)> core::fmt::Write for FixedLineStorage<N, F> {
+    /// Fails once the buffer is full, which stops the formatting early.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let free = N - self.len;
+        let mut cut = s.len().min(free);
+        while !s.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        self.buf[self.len..self.len + cut].copy_from_slice(&s.as_bytes()[..cut]);
+        self.len += cut;
+        if cut < s.len() {
+            self.truncated = true;
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize, F: FnMut(&str)> StorageProvider for FixedLineStorage<N, F> {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        self.len = 0;
+        self.truncated = false;
+        let _ = core::fmt::Write::write_fmt(self, args);
+        if self.truncated {
+            self.mark_truncated();
+        }
+        // Only whole characters were copied, so this cannot fail.
+        if let Ok(line) = core::str::from_utf8(&self.buf[..self.len]) {
+            (self.sink)(line);
+        }
+    }
+}
+
+#[cfg(feature = "rtt")]
+#[doc(hidden)]
+pub use rtt_target as __rtt_target;
+
+/// Where [`RttStorage`] writes to: an `rtt_target::UpChannel`, or a mock in
+/// host tests.
+#[cfg(feature = "rtt")]
+pub trait RttChannel {
+    fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+#[cfg(feature = "rtt")]
+impl RttChannel for rtt_target::UpChannel {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+}
+
+/// Logs over SEGGER RTT. Colors are kept, since RTT viewers render them.
+///
+/// [`new`](RttStorage::new) puts the channel in non-blocking mode, so output is
+/// dropped rather than stalling the firmware while no host reads the buffer.
+/// See [`rtt_init_cand!`] for a ready-made [`ULogger`].
+///
+/// ```
+/// use cand::{RttChannel, RttStorage, ULogger};
+///
+/// struct Mock(Vec<u8>);
+///
+/// impl RttChannel for Mock {
+///     fn write_bytes(&mut self, bytes: &[u8]) {
+///         self.0.extend_from_slice(bytes);
+///     }
+/// }
+///
+/// let mut logger = ULogger((), RttStorage(Mock(Vec::new())));
+/// logger.log_info("probe attached");
+/// assert!(String::from_utf8_lossy(&(logger.1).0.0).contains("probe attached"));
+/// ```
+#[cfg(feature = "rtt")]
+pub struct RttStorage<C: RttChannel = rtt_target::UpChannel>(pub C);
+
+#[cfg(feature = "rtt")]
+impl RttStorage {
+    /// Non-blocking: output that does not fit in the channel buffer is skipped.
+    pub fn new(mut channel: rtt_target::UpChannel) -> Self {
+        channel.set_mode(rtt_target::ChannelMode::NoBlockSkip);
+        RttStorage(channel)
+    }
+
+    /// Waits for the host whenever the channel buffer is full; only use this
+    /// while a debugger is attached.
+    pub fn blocking(mut channel: rtt_target::UpChannel) -> Self {
+        channel.set_mode(rtt_target::ChannelMode::BlockIfFull);
+        RttStorage(channel)
+    }
+}
+
+#[cfg(feature = "rtt")]
+impl<C: RttChannel> core::fmt::Write for RttStorage<C> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rtt")]
+impl<C: RttChannel> uWrite for RttStorage<C> {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.0.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rtt")]
+impl<C: RttChannel> StorageProvider for RttStorage<C> {
+    fn write_data(&mut self, args: Arguments, _debuglevel: &StatusLevel) {
+        let _ = core::fmt::Write::write_fmt(self, args);
+    }
+}
+
+#[cfg(feature = "rtt")]
+impl<C: RttChannel> UStorageProvider for RttStorage<C> {
+    fn write_data(&mut self, d: impl uDebug, _level: &StatusLevel) {
+        let _ = d.fmt(&mut ufmt::Formatter::new(self));
+    }
+}
+
+/// Sets up RTT with the default channels and returns a [`ULogger`] writing to
+/// up channel 0 through a non-blocking [`RttStorage`]. Call it once at startup:
+///
+/// ```ignore
+/// let mut logger = cand::rtt_init_cand!();
+/// // or with a time provider
+/// let mut logger = cand::rtt_init_cand!(TickClock::<SysTick>::now());
+/// logger.log_ok("booted");
+/// ```
+#[cfg(feature = "rtt")]
+#[macro_export]
+macro_rules! rtt_init_cand {
+    () => {
+        $crate::rtt_init_cand!(())
+    };
+    ($time:expr) => {{
+        let channels = $crate::__rtt_target::rtt_init_default!();
+        $crate::ULogger($time, $crate::RttStorage::new(channels.up.0))
+    }};
+}
+
+/// Hands each [`ULogger`] message to defmt, at `info` for `Ok` and `Info`, `warn`
+/// for `Warning` and `error` for `Error` and `Critical`.
+///
+/// The message alone, without cand's level symbol and time (defmt adds its own),
+/// is formatted into an `N`-byte buffer (longer ones are cut), its colors and
+/// trailing newline removed, and sent as a single `{=str}` argument.
+/// defmt interns only the format string, so unlike native defmt logging the
+/// whole text goes over the wire each time, and the formatting happens on the
+/// target.
+#[cfg(feature = "defmt")]
+pub struct DefmtStorage<const N: usize = 128> {
+    line: AnsiStripper<LineBuf<N>>,
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> DefmtStorage<N> {
+    pub fn new() -> Self {
+        Self {
+            line: AnsiStripper::new(LineBuf {
+                buf: [0; N],
+                len: 0,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> Default for DefmtStorage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> uWrite for DefmtStorage<N> {
+    type Error = core::convert::Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        let _ = core::fmt::Write::write_str(&mut self.line, s);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> UStorageProvider for DefmtStorage<N> {
+    fn write_record(&mut self, level: &StatusLevel, _elapsed: Duration, message: impl uDebug) {
+        self.write_data(message, level);
+    }
+
+    fn write_data(&mut self, d: impl uDebug, level: &StatusLevel) {
+        self.line.inner.len = 0;
+        self.line.state = EscapeState::Text;
+        let _ = d.fmt(&mut ufmt::Formatter::new(self));
+        let text = self.line.inner.as_str().trim_end_matches('\n');
+        match level {
+            StatusLevel::Ok | StatusLevel::Info => defmt::info!("{=str}", text),
+            StatusLevel::Warning => defmt::warn!("{=str}", text),
+            StatusLevel::Error | StatusLevel::Critical => defmt::error!("{=str}", text),
+        }
+    }
+}
+
+/// Records every message as `(level, text)` for assertions in tests.
+///
+/// Clones share the same records, so keep one clone and hand the other to the
+/// logger. Color codes are stripped, so assertions work with or without the
+/// `colors` feature. See [`assert_logged!`].
+///
+/// ```
+/// use cand::{set_color_mode, CaptureStorage, ColorMode, Logger, StatusLevel};
+///
+/// set_color_mode(ColorMode::Always);
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// logger.logdisp(StatusLevel::Info, "\x1b[1mbooted\x1b[0m in 3ms");
+/// logger.logdisp(StatusLevel::Error, "sensor offline");
+///
+/// assert_eq!(capture.len(), 2);
+/// assert!(capture.contains(StatusLevel::Error, "offline"));
+/// assert!(!capture.contains(StatusLevel::Info, "offline"));
+/// assert_eq!(capture.messages()[0], (StatusLevel::Info, "booted in 3ms".to_string()));
+/// capture.clear();
+/// assert!(capture.is_empty());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Default)]
+pub struct CaptureStorage(std::sync::Arc<std::sync::Mutex<Vec<(StatusLevel, String)>>>);
+
+#[cfg(feature = "std")]
+impl CaptureStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy of everything captured so far, oldest first.
+    pub fn messages(&self) -> Vec<(StatusLevel, String)> {
+        self.records().clone()
+    }
+
+    /// Whether a message at `level` containing `text` was captured.
+    pub fn contains(&self, level: StatusLevel, text: &str) -> bool {
+        self.records()
+            .iter()
+            .any(|(captured, message)| *captured == level && message.contains(text))
+    }
+
+    pub fn len(&self) -> usize {
+        self.records().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.records().clear();
+    }
+
+    fn records(&self) -> std::sync::MutexGuard<'_, Vec<(StatusLevel, String)>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn push(&mut self, level: StatusLevel, args: Arguments) {
+        let mut message = String::new();
+        let _ = core::fmt::Write::write_fmt(&mut AnsiStripper::new(&mut message), args);
+        self.records().push((level, message));
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageProvider for CaptureStorage {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.push(*debuglevel, args);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        self.push(record.level, record.message);
+    }
+}
+
+/// A message captured by [`RecordingStorageProvider`], owned so it can be
+/// inspected, filtered or replayed later.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub level: StatusLevel,
+    /// The message without level prefix, timestamp or colors.
+    pub message: alloc::string::String,
+    /// Time since the logger's [`TimeProvider`] was created, when it was logged.
+    pub timestamp: core::time::Duration,
+}
+
+#[cfg(feature = "alloc")]
+impl LogRecord {
+    /// Writes the record to `storage` as if it was logged again, stamped with
+    /// its original `timestamp`.
+    pub fn replay(&self, storage: &mut impl StorageProvider) {
+        if !storage.enabled(&self.level) {
+            return;
+        }
+        storage.write_record(&Record::new(
+            self.level,
+            self.timestamp,
+            &format_args!("{}:", HumanDuration(self.timestamp)),
+            format_args!("{}", self.message),
+        ));
+    }
+}
+
+/// Collects every message as a [`LogRecord`] for later inspection or replay.
+///
+/// ```
+/// use cand::{Logger, RecordingStorageProvider, StatusLevel};
+///
+/// let mut logger = Logger((), RecordingStorageProvider::new());
+/// logger.log_info("boot");
+/// logger.log_err("sensor missing");
+///
+/// let mut console = Logger((), ());
+/// for record in logger.1.drain().filter(|record| record.level >= StatusLevel::Error) {
+///     record.replay(&mut console.1);
+/// }
+/// assert!(logger.1.is_empty());
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Default)]
+pub struct RecordingStorageProvider {
+    records: alloc::vec::Vec<LogRecord>,
+}
+
+#[cfg(feature = "alloc")]
+impl RecordingStorageProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything recorded so far, oldest first.
+    pub fn records(&self) -> &[LogRecord] {
+        &self.records
+    }
+
+    /// Removes and returns all records, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = LogRecord> + '_ {
+        self.records.drain(..)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl StorageProvider for RecordingStorageProvider {
+    /// Lines written directly, not through a logger, are kept whole with a zero timestamp.
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let mut message = alloc::string::String::new();
+        let _ = core::fmt::Write::write_fmt(&mut AnsiStripper::new(&mut message), args);
+        self.records.push(LogRecord {
+            level: *debuglevel,
+            message,
+            timestamp: core::time::Duration::ZERO,
+        });
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        self.records.push(LogRecord {
+            level: record.level,
+            message: alloc::fmt::format(record.message),
+            timestamp: record.elapsed,
+        });
+    }
+}
+
+/// Asserts that a [`CaptureStorage`] holds a message at the given level
+/// containing the given text.
+///
+/// ```
+/// # use cand::{assert_logged, CaptureStorage, Logger, StatusLevel};
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// logger.logdisp(StatusLevel::Warning, "disk almost full");
+/// assert_logged!(capture, StatusLevel::Warning, "almost full");
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! assert_logged {
+    ($capture:expr, $level:expr, $text:expr $(,)?) => {{
+        let capture = &$capture;
+        let (level, text) = ($level, $text);
+        assert!(
+            capture.contains(level, text),
+            "expected a {} message containing {:?}, captured: {:?}",
+            level,
+            text,
+            capture
+                .messages()
+                .iter()
+                .map(|(level, message)| format!("{}: {}", level, message))
+                .collect::<Vec<_>>()
+        );
+    }};
+}
+
+/// Sends every message to both storages.
+///
+/// Each side only receives the levels it reports as [`enabled`](StorageProvider::enabled).
+/// The formatted line is passed through untouched, colors included, so give the
+/// plain-text side a storage that strips them, e.g.
+/// `TeeStorage((), FileStorage::new("app.log")?)`. Use [`tee!`] for more than two.
+///
+/// ```
+/// use cand::{set_color_mode, strip_ansi, tee, ColorMode, Logger, StatusLevel, StripAnsi, VecStorage};
+///
+/// set_color_mode(ColorMode::Always);
+/// let mut logger = Logger((), tee!(VecStorage::new(), VecStorage::new(), StripAnsi(VecStorage::new())));
+/// logger.logdisp(StatusLevel::Warning, "disk almost full");
+/// logger.log_kv(StatusLevel::Info, "mounted", &[("dev", &"sda1")]);
+///
+/// let (a, b, plain) = (&logger.1.0, &logger.1.1.0, &logger.1.1.1.0);
+/// assert_eq!(a.as_str(), b.as_str());
+/// assert!(a.as_str().contains("\x1b[") || !cfg!(feature = "colors"));
+/// assert_eq!(plain.as_str(), strip_ansi(a.as_str()));
+/// ```
+#[derive(Clone)]
+pub struct TeeStorage<A, B>(pub A, pub B);
+
+/// Chains any number of storages into nested [`TeeStorage`]s.
+///
+/// `tee!(a, b, c)` expands to `TeeStorage(a, TeeStorage(b, c))`.
+#[macro_export]
+macro_rules! tee {
+    ($storage:expr $(,)?) => {
+        $storage
+    };
+    ($first:expr, $($rest:expr),+ $(,)?) => {
+        $crate::TeeStorage($first, $crate::tee!($($rest),+))
+    };
+}
+
+impl<A: StorageProvider, B: StorageProvider> StorageProvider for TeeStorage<A, B> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        if self.0.enabled(debuglevel) {
+            self.0.write_data(args, debuglevel);
+        }
+        if self.1.enabled(debuglevel) {
+            self.1.write_data(args, debuglevel);
+        }
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        if self.0.enabled(&record.level) {
+            self.0.write_record(record);
+        }
+        if self.1.enabled(&record.level) {
+            self.1.write_record(record);
+        }
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.0.enabled(level) || self.1.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.0.flush();
+        self.1.flush();
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<A: UStorageProvider, B: UStorageProvider> UStorageProvider for TeeStorage<A, B> {
+    fn write_data(&mut self, d: impl uDebug, level: &StatusLevel) {
+        self.0.write_data(&d, level);
+        self.1.write_data(d, level);
+    }
+
+    fn write_record(&mut self, level: &StatusLevel, elapsed: Duration, message: impl uDebug) {
+        self.0.write_record(level, elapsed, &message);
+        self.1.write_record(level, elapsed, message);
+    }
+}
+
+/// Writes `src` into `dst` with all ANSI escape sequences removed.
+///
+/// ```
+/// use cand::strip_ansi_into;
+///
+/// let strip = |src: &str| {
+///     let mut out = String::new();
+///     strip_ansi_into(&mut out, src);
+///     out
+/// };
+/// assert_eq!(strip("\x1b[1;31mred \x1b[4munder\x1b[0m\x1b[0m"), "red under");
+/// assert_eq!(strip("\x1b[38;2;255;0;0mrgb\x1b[0m"), "rgb");
+/// // Unterminated sequences are dropped, and a new ESC starts over.
+/// assert_eq!(strip("cut \x1b[31"), "cut ");
+/// assert_eq!(strip("a\x1b[31\x1b[0mb"), "ab");
+/// ```
+pub fn strip_ansi_into(dst: &mut impl core::fmt::Write, src: &str) {
+    let _ = core::fmt::Write::write_str(&mut AnsiStripper::new(dst), src);
+}
+
+/// Returns `s` with all ANSI escape sequences removed, see [`strip_ansi_into`].
+#[cfg(feature = "alloc")]
+pub fn strip_ansi(s: &str) -> alloc::string::String {
+    let mut stripped = alloc::string::String::with_capacity(s.len());
+    strip_ansi_into(&mut stripped, s);
+    stripped
+}
+
+/// Displays the arguments with ANSI escape sequences removed, without buffering.
+struct Stripped<'a>(Arguments<'a>);
+
+impl Display for Stripped<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::write(&mut AnsiStripper::new(f), self.0)
+    }
+}
+
+/// Storage wrapper that removes color codes before the message reaches `S`.
+///
+/// Records stay records, so `S` still sees the level and fields; both the
+/// [`message`](Record::message) and the rendered line come without escapes.
+///
+/// ```
+/// use cand::{set_color_mode, ColorMode, Logger, Record, StatusLevel, StorageProvider, StripAnsi, VecStorage};
+///
+/// struct Messages(Vec<String>);
+///
+/// impl StorageProvider for Messages {
+///     fn write_data(&mut self, _: core::fmt::Arguments, _: &StatusLevel) {}
+///     fn write_record(&mut self, record: &Record) {
+///         self.0.push(record.message.to_string());
+///     }
+/// }
+///
+/// set_color_mode(ColorMode::Always);
+/// let mut lines = Logger((), StripAnsi(VecStorage::new()));
+/// lines.logdisp(StatusLevel::Warning, "\x1b[1mdisk\x1b[0m almost full");
+/// assert_eq!(lines.1.0.as_str(), "W&: disk almost full\n");
+///
+/// let mut messages = Logger((), StripAnsi(Messages(Vec::new())));
+/// messages.logdisp(StatusLevel::Warning, "\x1b[1mdisk\x1b[0m almost full");
+/// assert_eq!(messages.1.0.0, ["disk almost full"]);
+///
+/// // A sequence split between format arguments is still removed.
+/// lines.1.0.clear();
+/// lines.logdisp(StatusLevel::Ok, format_args!("{}{}", "\x1b[3", "2mdone\x1b[0m"));
+/// assert_eq!(lines.1.0.as_str(), "O&: done\n");
+/// ```
+#[derive(Clone)]
+pub struct StripAnsi<S: StorageProvider>(pub S);
+
+impl<S: StorageProvider> StorageProvider for StripAnsi<S> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.0
+            .write_data(format_args!("{}", Stripped(args)), debuglevel);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        self.0.write_record(&Record {
+            message: format_args!("{}", Stripped(record.message)),
+            strip: true,
+            ..*record
+        });
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.0.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.0.flush();
+    }
+}
+
+/// Storage wrapper that drops every message below `min_level`.
+///
+/// ```
+/// use cand::{CaptureStorage, LevelFilter, Logger, StatusLevel, TeeStorage};
+///
+/// let (console, file) = (CaptureStorage::new(), CaptureStorage::new());
+/// let storage = TeeStorage(console.clone(), LevelFilter::new(file.clone(), StatusLevel::Warning));
+/// let mut logger = Logger((), storage);
+/// logger.logdisp(StatusLevel::Info, "connected");
+/// logger.logdisp(StatusLevel::Error, "disconnected");
+///
+/// assert!(console.contains(StatusLevel::Info, "connected"));
+/// assert!(!file.contains(StatusLevel::Info, "connected"));
+/// assert!(console.contains(StatusLevel::Error, "disconnected") && file.contains(StatusLevel::Error, "disconnected"));
+/// ```
+#[derive(Clone)]
+pub struct LevelFilter<S: StorageProvider> {
+    pub inner: S,
+    pub min_level: StatusLevel,
+}
+
+impl<S: StorageProvider> LevelFilter<S> {
+    pub fn new(inner: S, min_level: StatusLevel) -> Self {
+        Self { inner, min_level }
+    }
+
+    /// Changes the threshold at runtime, e.g. `logger.1.set_min_level(StatusLevel::Ok)`.
+    pub fn set_min_level(&mut self, level: StatusLevel) {
+        self.min_level = level;
+    }
+}
+
+impl<S: StorageProvider> StorageProvider for LevelFilter<S> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        if self.enabled(debuglevel) {
+            self.inner.write_data(args, debuglevel);
+        }
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        if self.enabled(&record.level) {
+            self.inner.write_record(record);
+        }
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        *level >= self.min_level && self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
+
+/// Fixed-size text buffer that silently drops what does not fit, cutting at a
+/// character boundary.
+struct LineBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> LineBuf<N> {
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> core::fmt::Write for LineBuf<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut cut = s.len().min(N - self.len);
+        while !s.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        self.buf[self.len..self.len + cut].copy_from_slice(&s.as_bytes()[..cut]);
+        self.len += cut;
+        Ok(())
+    }
+}
+
+/// Decides per message whether it is written, see [`Logger::with_filter`].
+///
+/// Implemented for closures, so `|level, message: &str| !message.starts_with("[i2c]")`
+/// works as a filter.
+pub trait LogFilter {
+    /// `message` is the text without level prefix, timestamp or colors.
+    fn should_log(&self, level: StatusLevel, message: &str) -> bool;
+}
+
+impl<F: Fn(StatusLevel, &str) -> bool> LogFilter for F {
+    fn should_log(&self, level: StatusLevel, message: &str) -> bool {
+        self(level, message)
+    }
+}
+
+/// Storage wrapper that asks a [`LogFilter`] about each message.
+///
+/// The message is formatted into an `N`-byte stack buffer first, so the filter
+/// sees at most the first `N` bytes. Wrapping twice requires both filters to agree.
+///
+/// ```
+/// use cand::{Logger, StatusLevel, VecStorage};
+///
+/// let mut logger = Logger((), VecStorage::new())
+///     .with_filter(|_, message: &str| !message.contains("heartbeat"));
+/// logger.logdisp(StatusLevel::Info, "heartbeat 41");
+/// logger.logdisp(StatusLevel::Warning, "battery low");
+/// assert!(!logger.1.inner.as_str().contains("heartbeat"));
+/// assert!(logger.1.inner.as_str().contains("battery low"));
+/// ```
+#[derive(Clone)]
+pub struct Filtered<S: StorageProvider, F: LogFilter, const N: usize = 256> {
+    pub inner: S,
+    pub filter: F,
+}
+
+impl<S: StorageProvider, F: LogFilter, const N: usize> Filtered<S, F, N> {
+    pub fn new(inner: S, filter: F) -> Self {
+        Self { inner, filter }
+    }
+
+    fn should_log(&self, level: StatusLevel, message: Arguments) -> bool {
+        let mut line = AnsiStripper::new(LineBuf::<N> {
+            buf: [0; N],
+            len: 0,
+        });
+        let _ = core::fmt::Write::write_fmt(&mut line, message);
+        self.filter.should_log(level, line.inner.as_str())
+    }
+}
+
+impl<S: StorageProvider, F: LogFilter, const N: usize> StorageProvider for Filtered<S, F, N> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        if self.should_log(*debuglevel, args) {
+            self.inner.write_data(args, debuglevel);
+        }
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        if self.should_log(record.level, record.message) {
+            self.inner.write_record(record);
+        }
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
+
+/// Level, length, FNV-1a hash and the first `N` bytes of a message; two
+/// messages with equal fingerprints are treated as identical.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Fingerprint<const N: usize> {
+    level: StatusLevel,
+    len: usize,
+    hash: u64,
+    prefix: [u8; N],
+}
+
+impl<const N: usize> Fingerprint<N> {
+    fn of(record: &Record) -> Self {
+        let mut fingerprint = Fingerprint {
+            level: record.level,
+            len: 0,
+            hash: 0xcbf2_9ce4_8422_2325,
+            prefix: [0; N],
+        };
+        let _ = core::fmt::Write::write_fmt(&mut fingerprint, record.message);
+        for (key, value) in record.kv {
+            let _ = core::fmt::Write::write_fmt(&mut fingerprint, format_args!(" {key}={value:?}"));
+        }
+        fingerprint
+    }
+}
+
+impl<const N: usize> core::fmt::Write for Fingerprint<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &byte in s.as_bytes() {
+            if let Some(slot) = self.prefix.get_mut(self.len) {
+                *slot = byte;
+            }
+            self.len += 1;
+            self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3);
+        }
+        Ok(())
+    }
+}
+
+/// Storage wrapper that collapses repeated messages.
+///
+/// A message identical to the previous one (same level, message and key-value
+/// pairs; the time is ignored) is counted instead of written. The count goes
+/// out as `"last message repeated N times"` when a different message arrives,
+/// when the same message comes again after `window` has passed, on
+/// [`flush`](StorageProvider::flush) and on drop; the last two are stamped with
+/// `clock`. `Critical` messages are never suppressed.
+///
+/// Messages are compared through a fixed `N`-byte buffer plus a hash of the
+/// whole text, so this works without `alloc`. Only records from the loggers
+/// are collapsed; raw [`write_data`](StorageProvider::write_data) calls pass through.
+///
+/// ```
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use core::time::Duration;
+/// use cand::{CaptureStorage, Logger, RateLimited, StatusLevel, TickClock, TickSource, TimeProvider};
+///
+/// static MILLIS: AtomicU64 = AtomicU64::new(0);
+/// struct Millis;
+/// impl TickSource for Millis {
+///     const TICKS_PER_SECOND: u64 = 1_000;
+///     fn ticks() -> u64 {
+///         MILLIS.load(Ordering::Relaxed)
+///     }
+/// }
+///
+/// let capture = CaptureStorage::new();
+/// let storage = RateLimited::<_, _>::new(capture.clone(), TickClock::<Millis>::now(), Duration::from_secs(1));
+/// let mut logger = Logger((), storage);
+/// for _ in 0..1000 {
+///     logger.logdisp(StatusLevel::Warning, "sensor saturated");
+/// }
+/// logger.logdisp(StatusLevel::Critical, "overheat");
+/// logger.logdisp(StatusLevel::Critical, "overheat");
+/// logger.logdisp(StatusLevel::Info, "cooling down");
+/// let count = |level| capture.messages().iter().filter(|(l, _)| *l == level).count();
+/// assert_eq!(count(StatusLevel::Warning), 2);
+/// assert!(capture.contains(StatusLevel::Warning, "last message repeated 999 times"));
+/// assert_eq!(count(StatusLevel::Critical), 2);
+///
+/// // Once the window has passed, the same message is written again.
+/// logger.logdisp(StatusLevel::Info, "cooling down");
+/// MILLIS.store(1_500, Ordering::Relaxed);
+/// logger.logdisp(StatusLevel::Info, "cooling down");
+/// assert!(capture.contains(StatusLevel::Info, "last message repeated 1 time"));
+/// assert_eq!(count(StatusLevel::Info), 3);
+///
+/// // A pending count goes out when the storage is dropped.
+/// logger.logdisp(StatusLevel::Info, "cooling down");
+/// logger.logdisp(StatusLevel::Info, "cooling down");
+/// drop(logger);
+/// assert!(capture.contains(StatusLevel::Info, "last message repeated 2 times"));
+/// ```
+///
+/// On [`flush`](StorageProvider::flush) the count carries the time of `clock`:
+///
+/// ```
+/// # use core::sync::atomic::{AtomicU64, Ordering};
+/// # use core::time::Duration;
+/// # use cand::{strip_ansi, Logger, RateLimited, StatusLevel, TickClock, TickSource, TimeProvider, VecStorage};
+/// # static MILLIS: AtomicU64 = AtomicU64::new(0);
+/// # struct Millis;
+/// # impl TickSource for Millis {
+/// #     const TICKS_PER_SECOND: u64 = 1_000;
+/// #     fn ticks() -> u64 {
+/// #         MILLIS.load(Ordering::Relaxed)
+/// #     }
+/// # }
+/// let storage = RateLimited::<_, _>::new(VecStorage::new(), TickClock::<Millis>::now(), Duration::from_secs(1));
+/// let mut logger = Logger((), storage);
+/// logger.logdisp(StatusLevel::Warning, "fan stalled");
+/// logger.logdisp(StatusLevel::Warning, "fan stalled");
+/// MILLIS.store(250, Ordering::Relaxed);
+/// logger.flush();
+/// let out = strip_ansi(logger.1.inner.as_str());
+/// assert_eq!(out, "W&: fan stalled\nW&:0.250s: last message repeated 1 time\n");
+/// ```
+#[derive(Clone)]
+pub struct RateLimited<S: StorageProvider, C: TimeProvider, const N: usize = 64> {
+    pub inner: S,
+    clock: C,
+    window: core::time::Duration,
+    last: Option<Fingerprint<N>>,
+    window_start: core::time::Duration,
+    repeated: u32,
+}
+
+impl<S: StorageProvider, C: TimeProvider, const N: usize> RateLimited<S, C, N> {
+    /// `clock` decides when `window` has passed, e.g. `Instant::now()` or a [`TickClock`].
+    pub fn new(inner: S, clock: C, window: core::time::Duration) -> Self {
+        Self {
+            inner,
+            clock,
+            window,
+            last: None,
+            window_start: core::time::Duration::ZERO,
+            repeated: 0,
+        }
+    }
+
+    /// Writes the pending `"last message repeated N times"`, if any.
+    fn write_summary(&mut self, elapsed: core::time::Duration, time: &dyn Display) {
+        Self::summarize(
+            &mut self.inner,
+            &mut self.repeated,
+            self.last,
+            elapsed,
+            time,
+        );
+    }
+
+    /// [`write_summary`](Self::write_summary) stamped with `clock`.
+    fn write_summary_now(&mut self) {
+        let elapsed = self.clock.elapsed();
+        let time = TimeFormatter(&self.clock);
+        Self::summarize(
+            &mut self.inner,
+            &mut self.repeated,
+            self.last,
+            elapsed,
+            &time,
+        );
+    }
+
+    fn summarize(
+        inner: &mut S,
+        repeated: &mut u32,
+        last: Option<Fingerprint<N>>,
+        elapsed: core::time::Duration,
+        time: &dyn Display,
+    ) {
+        let Some(last) = last.filter(|_| *repeated > 0) else {
+            return;
+        };
+        let count = core::mem::take(repeated);
+        let times = if count == 1 { "time" } else { "times" };
+        inner.write_record(&Record::new(
+            last.level,
+            elapsed,
+            time,
+            format_args!("last message repeated {count} {times}"),
+        ));
+    }
+}
+
+impl<S: StorageProvider, C: TimeProvider, const N: usize> StorageProvider for RateLimited<S, C, N> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.inner.write_data(args, debuglevel);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        let now = self.clock.elapsed();
+        let fingerprint = Fingerprint::of(record);
+        if record.level != StatusLevel::Critical
+            && self.last == Some(fingerprint)
+            && now.saturating_sub(self.window_start) < self.window
+        {
+            self.repeated = self.repeated.saturating_add(1);
+            return;
+        }
+        self.write_summary(record.elapsed, record.time);
+        self.inner.write_record(record);
+        self.last = Some(fingerprint);
+        self.window_start = now;
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.write_summary_now();
+        self.inner.flush();
+    }
+}
+
+impl<S: StorageProvider, C: TimeProvider, const N: usize> Drop for RateLimited<S, C, N> {
+    fn drop(&mut self) {
+        self.write_summary_now();
+    }
+}
+
+/// Storage wrapper that counts the messages it forwards, per level.
+#[derive(Clone)]
+pub struct StatStorageProvider<S: StorageProvider> {
+    pub inner: S,
+    counts: [u64; 5],
+}
+
+impl<S: StorageProvider> StatStorageProvider<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            counts: [0; 5],
+        }
+    }
+
+    pub fn stats(&self) -> LogStats {
+        LogStats(self.counts)
+    }
+
+    fn count(&mut self, level: StatusLevel) {
+        self.counts[level as usize] += 1;
+    }
+}
+
+impl<S: StorageProvider> StorageProvider for StatStorageProvider<S> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        self.count(*debuglevel);
+        self.inner.write_data(args, debuglevel);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        self.count(record.level);
+        self.inner.write_record(record);
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
+
+/// Message counts collected by [`StatStorageProvider`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogStats(pub [u64; 5]);
+
+impl LogStats {
+    pub fn count(&self, level: StatusLevel) -> u64 {
+        self.0[level as usize]
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.count(StatusLevel::Error)
+    }
+
+    pub fn warnings(&self) -> u64 {
+        self.count(StatusLevel::Warning)
+    }
+
+    pub fn criticals(&self) -> u64 {
+        self.count(StatusLevel::Critical)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.iter().sum()
+    }
+}
+
+/// Storage wrapper that collects formatted lines and hands them to `S` in bulk,
+/// once more than `capacity` bytes are buffered, a message at or above the flush
+/// level (`Warning` unless changed with [`with_flush_level`](Self::with_flush_level))
+/// arrives, on [`flush`](StorageProvider::flush) and on drop. `Critical` messages
+/// are always written straight away.
+///
+/// ```
+/// # use core::sync::atomic::{AtomicUsize, Ordering};
+/// use cand::{BufferedStorageProvider, Logger, StatusLevel, StorageProvider};
+///
+/// static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Count;
+///
+/// impl StorageProvider for Count {
+///     fn write_data(&mut self, args: core::fmt::Arguments, _: &StatusLevel) {
+///         WRITTEN.fetch_add(args.to_string().lines().count(), Ordering::Relaxed);
+///     }
+/// }
+///
+/// let mut logger = Logger((), BufferedStorageProvider::new(Count, 4096));
+/// logger.logdisp(StatusLevel::Info, "one");
+/// logger.logdisp(StatusLevel::Info, "two");
+/// assert_eq!(WRITTEN.load(Ordering::Relaxed), 0);
+/// drop(logger);
+/// assert_eq!(WRITTEN.load(Ordering::Relaxed), 2);
+/// ```
+///
+/// Buffered lines keep their own level: consecutive lines of the same level are
+/// written together. They reach `S` as rendered text, so structured storages
+/// lose their separate fields, except for messages at or above the flush level,
+/// which are forwarded as records:
+///
+/// ```
+/// use cand::{BufferedStorageProvider, Logger, Record, StatusLevel, StorageProvider};
+///
+/// #[derive(Default)]
+/// struct Writes(Vec<(StatusLevel, String)>);
+///
+/// impl StorageProvider for Writes {
+///     fn write_data(&mut self, args: core::fmt::Arguments, level: &StatusLevel) {
+///         self.0.push((*level, args.to_string()));
+///     }
+///
+///     fn write_record(&mut self, record: &Record) {
+///         self.0.push((record.level, format!("record: {}", record.message)));
+///     }
+/// }
+///
+/// let mut logger = Logger((), BufferedStorageProvider::new(Writes::default(), 4096));
+/// logger.logdisp(StatusLevel::Ok, "a");
+/// logger.logdisp(StatusLevel::Ok, "b");
+/// logger.logdisp(StatusLevel::Info, "c");
+/// logger.logdisp(StatusLevel::Error, "d");
+/// let writes: Vec<_> = logger.1.inner.0.iter().map(|(level, text)| (*level, cand::strip_ansi(text))).collect();
+/// assert_eq!(
+///     writes,
+///     [
+///         (StatusLevel::Ok, "O&: a\nO&: b\n".to_string()),
+///         (StatusLevel::Info, "I&: c\n".to_string()),
+///         (StatusLevel::Error, "record: d".to_string()),
+///     ]
+/// );
+/// ```
+///
+/// Nothing is lost when the storage is dropped, whether or not the buffer
+/// filled up in between:
+///
+/// ```
+/// use cand::{BufferedStorageProvider, CaptureStorage, Logger, StatusLevel};
+///
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), BufferedStorageProvider::new(capture.clone(), 64));
+/// let mut expected = String::new();
+/// for i in 0..100 {
+///     let level = if i % 3 == 0 { StatusLevel::Ok } else { StatusLevel::Info };
+///     logger.logdisp(level, format_args!("line {i}"));
+///     expected += &format!("{level:?} line {i}\n");
+/// }
+/// let written = |capture: &CaptureStorage| capture.messages().into_iter().map(|(_, text)| text).collect::<String>();
+/// assert!(written(&capture).len() < expected.len());
+/// drop(logger);
+/// assert_eq!(written(&capture), expected);
+/// ```
+#[cfg(feature = "alloc")]
+pub struct BufferedStorageProvider<S: StorageProvider> {
+    pub inner: S,
+    buffer: alloc::string::String,
+    /// Level and end offset in `buffer` of each buffered line.
+    lines: alloc::vec::Vec<(StatusLevel, usize)>,
+    capacity: usize,
+    flush_level: StatusLevel,
+}
+
+#[cfg(feature = "alloc")]
+impl<S: StorageProvider> BufferedStorageProvider<S> {
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner,
+            buffer: alloc::string::String::with_capacity(capacity),
+            lines: alloc::vec::Vec::new(),
+            capacity,
+            flush_level: StatusLevel::Warning,
+        }
+    }
+
+    /// Sets the level from which a message flushes the buffer.
+    pub fn with_flush_level(mut self, flush_level: StatusLevel) -> Self {
+        self.flush_level = flush_level;
+        self
+    }
+
+    /// Hands the buffered lines to `S`, one write per run of the same level.
+    fn write_buffered(&mut self) {
+        let mut start = 0;
+        for (i, &(level, end)) in self.lines.iter().enumerate() {
+            if self
+                .lines
+                .get(i + 1)
+                .is_some_and(|&(next, _)| next == level)
+            {
+                continue;
+            }
+            self.inner
+                .write_data(format_args!("{}", &self.buffer[start..end]), &level);
+            start = end;
+        }
+        self.lines.clear();
+        self.buffer.clear();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: StorageProvider> StorageProvider for BufferedStorageProvider<S> {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let _ = core::fmt::Write::write_fmt(&mut self.buffer, args);
+        self.lines.push((*debuglevel, self.buffer.len()));
+        if self.buffer.len() > self.capacity || *debuglevel >= self.flush_level {
+            self.flush();
+        }
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        if record.level >= self.flush_level {
+            self.write_buffered();
+            self.inner.write_record(record);
+            self.inner.flush();
+        } else {
+            self.write_data(format_args!("{record}"), &record.level);
+        }
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        self.write_buffered();
+        self.inner.flush();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: StorageProvider> Drop for BufferedStorageProvider<S> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A storage whose writes can fail, e.g. a full disk or a dropped connection.
+///
+/// Wrap it in [`FallibleStorage`] to use it with a [`Logger`]; the wrapper
+/// decides what happens to errors through its [`ErrorPolicy`].
+pub trait TryStorageProvider {
+    type Error;
+
+    fn try_write_data(
+        &mut self,
+        args: Arguments,
+        debuglevel: &StatusLevel,
+    ) -> Result<(), Self::Error>;
+
+    /// Same as [`StorageProvider::write_record`]; the default renders the line
+    /// for [`try_write_data`](Self::try_write_data).
+    fn try_write_record(&mut self, record: &Record) -> Result<(), Self::Error> {
+        self.try_write_data(format_args!("{record}"), &record.level)
+    }
+
+    /// Same as [`StorageProvider::enabled`].
+    fn enabled(&self, _level: &StatusLevel) -> bool {
+        true
+    }
+
+    fn try_flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryStorageProvider for () {
+    type Error = core::convert::Infallible;
+
+    fn try_write_data(
+        &mut self,
+        args: Arguments,
+        debuglevel: &StatusLevel,
+    ) -> Result<(), Self::Error> {
+        StorageProvider::write_data(self, args, debuglevel);
+        Ok(())
+    }
+
+    fn try_write_record(&mut self, record: &Record) -> Result<(), Self::Error> {
+        StorageProvider::write_record(self, record);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> TryStorageProvider for IoWriter<W> {
+    type Error = std::io::Error;
+
+    fn try_write_data(
+        &mut self,
+        args: Arguments,
+        _debuglevel: &StatusLevel,
+    ) -> std::io::Result<()> {
+        self.0.write_fmt(args)
+    }
+
+    fn try_flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryStorageProvider for FileStorage {
+    type Error = std::io::Error;
+
+    fn try_write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) -> std::io::Result<()> {
+        if self.echo {
+            print!("{args}");
+        }
+        let mut out = IoFmt::new(&mut self.file);
+        if core::fmt::Write::write_fmt(&mut AnsiStripper::new(&mut out), args).is_err() {
+            return Err(out.take_error());
+        }
+        if *debuglevel >= StatusLevel::Error {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn try_flush(&mut self) -> std::io::Result<()> {
+        FileStorage::flush(self)
+    }
+}
+
+/// What [`FallibleStorage`] does when a write fails. The error is kept for
+/// [`last_error`](FallibleStorage::last_error) in every case.
+pub enum ErrorPolicy<E> {
+    Ignore,
+    PanicOnError,
+    Callback(fn(&E)),
+}
+
+impl<E> Clone for ErrorPolicy<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for ErrorPolicy<E> {}
+
+/// Adapts a [`TryStorageProvider`] into a [`StorageProvider`], handling errors
+/// according to its [`ErrorPolicy`]. Records and [`enabled`](TryStorageProvider::enabled)
+/// are passed through to the inner storage:
+///
+/// ```
+/// use cand::{ErrorPolicy, FallibleStorage, Logger, Record, StatusLevel, TryStorageProvider};
+///
+/// struct Keys(Vec<String>);
+///
+/// impl TryStorageProvider for Keys {
+///     type Error = ();
+///
+///     fn try_write_data(&mut self, _: core::fmt::Arguments, _: &StatusLevel) -> Result<(), ()> {
+///         Ok(())
+///     }
+///
+///     fn try_write_record(&mut self, record: &Record) -> Result<(), ()> {
+///         self.0.extend(record.kv.iter().map(|(key, _)| key.to_string()));
+///         Err(())
+///     }
+///
+///     fn enabled(&self, level: &StatusLevel) -> bool {
+///         *level >= StatusLevel::Error
+///     }
+/// }
+///
+/// let mut logger = Logger((), FallibleStorage::new(Keys(Vec::new()), ErrorPolicy::Ignore));
+/// logger.log_kv(StatusLevel::Info, "skipped", &[("a", &1)]);
+/// logger.log_kv(StatusLevel::Error, "kept", &[("b", &2)]);
+/// assert_eq!(logger.1.inner.0, ["b"]);
+/// assert_eq!(logger.1.last_error(), Some(&()));
+/// ```
+pub struct FallibleStorage<S: TryStorageProvider> {
+    pub inner: S,
+    policy: ErrorPolicy<S::Error>,
+    last_error: Option<S::Error>,
+}
+
+impl<S: TryStorageProvider + Clone> Clone for FallibleStorage<S>
+where
+    S::Error: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            policy: self.policy,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+impl<S: TryStorageProvider> FallibleStorage<S> {
+    pub fn new(inner: S, policy: ErrorPolicy<S::Error>) -> Self {
+        Self {
+            inner,
+            policy,
+            last_error: None,
+        }
+    }
+
+    /// The most recent write error, if any.
+    pub fn last_error(&self) -> Option<&S::Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Takes the most recent write error, clearing it.
+    pub fn take_error(&mut self) -> Option<S::Error> {
+        self.last_error.take()
+    }
+}
+
+impl<S: TryStorageProvider> FallibleStorage<S>
+where
+    S::Error: Debug,
+{
+    fn handle(&mut self, result: Result<(), S::Error>) {
+        if let Err(err) = result {
+            match self.policy {
+                ErrorPolicy::Ignore => {}
+                ErrorPolicy::PanicOnError => panic!("log storage failed: {err:?}"),
+                ErrorPolicy::Callback(callback) => callback(&err),
+            }
+            self.last_error = Some(err);
+        }
+    }
+}
+
+impl<S: TryStorageProvider> StorageProvider for FallibleStorage<S>
+where
+    S::Error: Debug,
+{
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let result = self.inner.try_write_data(args, debuglevel);
+        self.handle(result);
+    }
+
+    fn write_record(&mut self, record: &Record) {
+        let result = self.inner.try_write_record(record);
+        self.handle(result);
+    }
+
+    fn enabled(&self, level: &StatusLevel) -> bool {
+        self.inner.enabled(level)
+    }
+
+    fn flush(&mut self) {
+        let result = self.inner.try_flush();
+        self.handle(result);
+    }
+}
+
+/// What [`AsyncStorage`] does with a message when its queue is full.
+///
+/// ```
+/// # use std::sync::mpsc;
+/// # use std::time::Duration;
+/// # use cand::{AsyncStorage, CaptureStorage, Logger, OverflowPolicy, StatusLevel, StorageProvider};
+/// # struct Stalled(CaptureStorage, mpsc::Sender<()>, mpsc::Receiver<()>);
+/// # impl StorageProvider for Stalled {
+/// #     fn write_data(&mut self, args: core::fmt::Arguments, level: &StatusLevel) {
+/// #         let _ = self.1.send(());
+/// #         let _ = self.2.recv_timeout(Duration::from_secs(5));
+/// #         self.0.write_data(args, level);
+/// #     }
+/// # }
+/// # let capture = CaptureStorage::new();
+/// # let (started, writing) = mpsc::channel();
+/// # let (release, gate) = mpsc::channel();
+/// // The writer thread is stuck on "first" and the queue holds two lines.
+/// let storage = AsyncStorage::new(Stalled(capture.clone(), started, gate), 2, OverflowPolicy::DropOldest);
+/// let mut logger = Logger((), storage);
+/// logger.log_critical("first");
+/// # writing.recv_timeout(Duration::from_secs(5)).unwrap();
+/// logger.log_critical("a");
+/// logger.log_critical("b");
+/// logger.log_critical("c");
+/// assert_eq!(logger.1.dropped(), 1);
+/// # drop(release);
+/// logger.1.shutdown();
+/// assert!(!capture.contains(StatusLevel::Critical, "a"));
+/// assert!(capture.contains(StatusLevel::Critical, "b") && capture.contains(StatusLevel::Critical, "c"));
+/// assert!(capture.contains(StatusLevel::Warning, "1 log messages dropped"));
+/// ```
+///
+/// With `Block` the logging thread waits for the writer instead:
+///
+/// ```
+/// # use std::sync::mpsc;
+/// # use std::time::Duration;
+/// # use cand::{AsyncStorage, CaptureStorage, Logger, OverflowPolicy, StatusLevel, StorageProvider};
+/// # struct Stalled(CaptureStorage, mpsc::Sender<()>, mpsc::Receiver<()>);
+/// # impl StorageProvider for Stalled {
+/// #     fn write_data(&mut self, args: core::fmt::Arguments, level: &StatusLevel) {
+/// #         let _ = self.1.send(());
+/// #         let _ = self.2.recv_timeout(Duration::from_secs(5));
+/// #         self.0.write_data(args, level);
+/// #     }
+/// # }
+/// # let capture = CaptureStorage::new();
+/// # let (started, writing) = mpsc::channel();
+/// # let (release, gate) = mpsc::channel();
+/// let storage = AsyncStorage::new(Stalled(capture.clone(), started, gate), 2, OverflowPolicy::Block);
+/// let mut logger = Logger((), storage);
+/// logger.log_critical("first");
+/// # writing.recv_timeout(Duration::from_secs(5)).unwrap();
+/// let (done, finished) = mpsc::channel();
+/// let producer = std::thread::spawn(move || {
+///     for line in ["a", "b", "c"] {
+///         logger.log_critical(line);
+///     }
+///     done.send(()).unwrap();
+///     logger
+/// });
+/// // "c" does not fit while the writer is stuck.
+/// assert!(finished.recv_timeout(Duration::from_millis(100)).is_err());
+/// drop(release);
+/// finished.recv_timeout(Duration::from_secs(5)).unwrap();
+///
+/// let mut logger = producer.join().unwrap();
+/// logger.1.shutdown();
+/// assert_eq!(logger.1.dropped(), 0);
+/// assert_eq!(capture.len(), 4);
+/// assert!(capture.contains(StatusLevel::Critical, "c"));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discards the message being logged.
+    DropNewest,
+    /// Discards the oldest queued message to make room.
+    DropOldest,
+    /// Waits until the writer thread has made room.
+    Block,
+}
+
+#[cfg(feature = "std")]
+struct AsyncQueue {
+    state: std::sync::Mutex<AsyncState>,
+    changed: std::sync::Condvar,
+}
+
+#[cfg(feature = "std")]
+struct AsyncState {
+    messages: std::collections::VecDeque<(StatusLevel, String)>,
+    capacity: usize,
+    dropped: u64,
+    reported: u64,
+    writing: bool,
+    closed: bool,
+}
+
+#[cfg(feature = "std")]
+impl AsyncQueue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, AsyncState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn wait<'a>(
+        &self,
+        state: std::sync::MutexGuard<'a, AsyncState>,
+    ) -> std::sync::MutexGuard<'a, AsyncState> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Writes until shutdown. If `inner` panics the queue is closed, so `flush`
+    /// and [`OverflowPolicy::Block`] don't wait for a thread that is gone.
+    fn run(&self, inner: impl StorageProvider) {
+        let written =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.write_all(inner)));
+        if written.is_err() {
+            let mut state = self.lock();
+            state.dropped += state.messages.len() as u64;
+            state.messages.clear();
+            state.writing = false;
+            state.closed = true;
+            self.changed.notify_all();
+        }
+    }
+
+    fn write_all(&self, mut inner: impl StorageProvider) {
+        let mut state = self.lock();
+        loop {
+            if state.messages.is_empty() {
+                if state.closed {
+                    break;
+                }
+                state = self.wait(state);
+                continue;
+            }
+            let batch = core::mem::take(&mut state.messages);
+            state.writing = true;
+            self.changed.notify_all();
+            drop(state);
+
+            for (level, line) in &batch {
+                inner.write_data(format_args!("{line}"), level);
+            }
+
+            state = self.lock();
+            let dropped = state.dropped - state.reported;
+            let drained = state.messages.is_empty();
+            if drained {
+                state.reported = state.dropped;
+            }
+            drop(state);
+
+            if dropped > 0 && drained {
+                inner.write_record(&Record::new(
+                    StatusLevel::Warning,
+                    core::time::Duration::ZERO,
+                    &"",
+                    format_args!("{dropped} log messages dropped"),
+                ));
+            }
+            inner.flush();
+
+            state = self.lock();
+            state.writing = false;
+            self.changed.notify_all();
+        }
+        inner.flush();
+    }
+}
+
+/// Hands formatted lines to a writer thread that passes them on to `S`, so
+/// logging only costs formatting and a queue push.
+///
+/// At most `capacity` lines wait in the queue; beyond that the [`OverflowPolicy`]
+/// applies. The number of dropped lines is logged as a `Warning` once the queue
+/// has drained. [`shutdown`](Self::shutdown) and drop write out everything queued
+/// and join the thread.
+///
+/// If `S` panics, the writer thread stops and every later message is discarded
+/// and counted as dropped; logging and [`flush`](StorageProvider::flush) keep returning.
+///
+/// ```
+/// use cand::{AsyncStorage, Logger, OverflowPolicy, StatusLevel, StorageProvider};
+///
+/// struct Broken;
+///
+/// impl StorageProvider for Broken {
+///     fn write_data(&mut self, _: core::fmt::Arguments, _: &StatusLevel) {
+///         panic!("disk on fire");
+///     }
+/// }
+///
+/// let mut logger = Logger((), AsyncStorage::new(Broken, 1, OverflowPolicy::Block));
+/// logger.log_critical("lost with the writer thread");
+/// logger.flush();
+/// logger.log_critical("a");
+/// logger.log_critical("b");
+/// logger.flush();
+/// assert_eq!(logger.1.dropped(), 2);
+/// ```
+///
+/// ```
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+/// use cand::{AsyncStorage, CaptureStorage, Logger, OverflowPolicy, StatusLevel, StorageProvider};
+///
+/// // Holds the writer thread in its first write until `release` is dropped.
+/// struct Stalled(CaptureStorage, mpsc::Sender<()>, mpsc::Receiver<()>);
+///
+/// impl StorageProvider for Stalled {
+///     fn write_data(&mut self, args: core::fmt::Arguments, level: &StatusLevel) {
+///         let _ = self.1.send(());
+///         let _ = self.2.recv_timeout(Duration::from_secs(5));
+///         self.0.write_data(args, level);
+///     }
+///     fn write_record(&mut self, record: &cand::Record) {
+///         self.0.write_record(record);
+///     }
+/// }
+///
+/// let capture = CaptureStorage::new();
+/// let (started, writing) = mpsc::channel();
+/// let (release, gate) = mpsc::channel();
+/// let storage = AsyncStorage::new(Stalled(capture.clone(), started, gate), 2, OverflowPolicy::DropNewest);
+/// let mut logger = Logger((), storage);
+///
+/// logger.log_critical("first");
+/// writing.recv_timeout(Duration::from_secs(5)).unwrap();
+/// logger.log_critical("a");
+/// logger.log_critical("b");
+/// logger.log_critical("c");
+/// assert_eq!(logger.1.dropped(), 1);
+///
+/// drop(release);
+/// logger.1.shutdown();
+/// let lines: Vec<_> = capture.messages().into_iter().map(|(_, line)| line).collect();
+/// assert_eq!(lines.len(), 4);
+/// assert!(lines[0].contains("first") && lines[1].contains("a") && lines[2].contains("b"));
+/// assert_eq!(lines[3], "1 log messages dropped");
+/// ```
+#[cfg(feature = "std")]
+pub struct AsyncStorage {
+    queue: std::sync::Arc<AsyncQueue>,
+    policy: OverflowPolicy,
+    worker: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "std")]
+impl AsyncStorage {
+    /// Spawns the writer thread, which owns `inner` until shutdown.
+    pub fn new<S: StorageProvider + Send + 'static>(
+        inner: S,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> Self {
+        let queue = std::sync::Arc::new(AsyncQueue {
+            state: std::sync::Mutex::new(AsyncState {
+                messages: std::collections::VecDeque::with_capacity(capacity),
+                capacity: capacity.max(1),
+                dropped: 0,
+                reported: 0,
+                writing: false,
+                closed: false,
+            }),
+            changed: std::sync::Condvar::new(),
+        });
+        let worker = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.run(inner))
+        };
+        Self {
+            queue,
+            policy,
+            worker: Some(worker),
+        }
+    }
+
+    /// Lines dropped because the queue was full, since creation.
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+
+    /// Writes out everything queued and stops the writer thread. Later messages
+    /// are discarded.
+    pub fn shutdown(&mut self) {
+        self.queue.lock().closed = true;
+        self.queue.changed.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageProvider for AsyncStorage {
+    fn write_data(&mut self, args: Arguments, debuglevel: &StatusLevel) {
+        let line = args.to_string();
+        let mut state = self.queue.lock();
+        while !state.closed && state.messages.len() >= state.capacity {
+            match self.policy {
+                OverflowPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                }
+                OverflowPolicy::DropOldest => {
+                    state.messages.pop_front();
+                    state.dropped += 1;
+                }
+                OverflowPolicy::Block => state = self.queue.wait(state),
+            }
+        }
+        if state.closed {
+            // Not shut down, so the writer thread died.
+            if self.worker.is_some() {
+                state.dropped += 1;
+            }
+            return;
+        }
+        state.messages.push_back((*debuglevel, line));
+        self.queue.changed.notify_all();
+    }
+
+    /// Waits until the writer thread has written everything queued so far.
+    fn flush(&mut self) {
+        let mut state = self.queue.lock();
+        while self.worker.is_some() && (!state.messages.is_empty() || state.writing) {
+            state = self.queue.wait(state);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for AsyncStorage {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+pub trait TimeProvider {
+    fn now() -> Self;
+    fn elapsed(&self) -> core::time::Duration;
+    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result;
+}
+
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "std")]
+impl TimeProvider for Instant {
+    fn now() -> Self {
+        Instant::now()
+    }
+    fn elapsed(&self) -> core::time::Duration {
+        self.elapsed()
+    }
+    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}:", HumanDuration(self.elapsed()))
+    }
+}
+
+/// Stamps messages with the UTC wall-clock time, e.g. `2024-02-29T13:05:09.042Z:`.
+///
+/// The time is read from `SystemTime` when the message is written; `elapsed`
+/// still counts from construction.
+///
+/// ```
+/// use cand::{strip_ansi, Logger, StatusLevel, TimeProvider, VecStorage, WallClock};
+///
+/// let mut logger = Logger(WallClock::now(), VecStorage::new());
+/// logger.logdisp(StatusLevel::Info, "booted");
+/// let out = strip_ansi(logger.1.as_str());
+/// let (time, message) = out.strip_prefix("I&:").unwrap().split_once(' ').unwrap();
+/// assert_eq!((time.len(), &time[10..11], &time[23..]), (25, "T", "Z:"));
+/// assert_eq!(message, "booted\n");
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct WallClock(pub Instant);
+
+#[cfg(feature = "std")]
+impl TimeProvider for WallClock {
+    fn now() -> Self {
+        WallClock(Instant::now())
+    }
+    fn elapsed(&self) -> core::time::Duration {
+        self.0.elapsed()
+    }
+    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(f, "{}:", Rfc3339(since_epoch))
+    }
+}
+
+/// Stamps messages with the Unix time in seconds and milliseconds, e.g. `1709211909.042:`.
+///
+/// Unlike [`WallClock`], `elapsed` follows `SystemTime`, so it can jump if the
+/// system clock is changed; it saturates at zero when the clock goes backwards.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct SystemTimeProvider {
+    pub start: std::time::SystemTime,
+}
+
+#[cfg(feature = "std")]
+impl TimeProvider for SystemTimeProvider {
+    fn now() -> Self {
+        SystemTimeProvider {
+            start: std::time::SystemTime::now(),
+        }
+    }
+    fn elapsed(&self) -> core::time::Duration {
+        std::time::SystemTime::now()
+            .duration_since(self.start)
+            .unwrap_or(core::time::Duration::ZERO)
+    }
+    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{}.{:03}:",
+            since_epoch.as_secs(),
+            since_epoch.subsec_millis()
+        )
+    }
+}
+
+/// Displays a time since the Unix epoch through [`write_rfc3339`].
+#[cfg(feature = "std")]
+struct Rfc3339(core::time::Duration);
+
+#[cfg(feature = "std")]
+impl Display for Rfc3339 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_rfc3339(f, self.0)
+    }
+}
+
+/// Formats a time since the Unix epoch as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+#[cfg(feature = "std")]
+fn write_rfc3339(
+    f: &mut core::fmt::Formatter,
+    since_epoch: core::time::Duration,
+) -> core::fmt::Result {
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Days to civil date, from Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    write!(
+        f,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// A monotonic hardware counter for [`TickClock`], e.g. a SysTick millisecond count.
+///
+/// ```
+/// # use core::sync::atomic::{AtomicU32, Ordering};
+/// static MILLIS: AtomicU32 = AtomicU32::new(0); // bumped by the SysTick interrupt
+///
+/// struct SysTick;
+///
+/// impl cand::TickSource for SysTick {
+///     const TICKS_PER_SECOND: u64 = 1_000;
+///     const BITS: u32 = 32;
+///     fn ticks() -> u64 {
+///         MILLIS.load(Ordering::Relaxed) as u64
+///     }
+/// }
+///
+/// let mut logger = cand::Logger(<cand::TickClock<SysTick> as cand::TimeProvider>::now(), ());
+/// ```
+pub trait TickSource {
+    /// Counter frequency, e.g. `1_000` for milliseconds or `32_768` for an RTC.
+    const TICKS_PER_SECOND: u64;
+    /// Width of the counter; the clock handles it wrapping around at `2^BITS`.
+    const BITS: u32 = 64;
+    /// Current counter value.
+    fn ticks() -> u64;
+}
+
+/// [`TimeProvider`] for targets without `Instant`, counting ticks of `C` since creation.
+///
+/// Only one wraparound of the counter can be told apart, so a 32-bit millisecond
+/// counter stays correct for about 49 days.
+///
+/// ```
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use core::time::Duration;
+/// use cand::{strip_ansi, MultiLogger, StatusLevel, TickClock, TickSource, TimeProvider, VecStorage};
+///
+/// static MILLIS: AtomicU32 = AtomicU32::new(u32::MAX - 499);
+///
+/// struct Fake;
+///
+/// impl TickSource for Fake {
+///     const TICKS_PER_SECOND: u64 = 1_000;
+///     const BITS: u32 = 32;
+///     fn ticks() -> u64 {
+///         MILLIS.load(Ordering::Relaxed) as u64
+///     }
+/// }
+///
+/// let mut logger = MultiLogger(TickClock::<Fake>::now(), VecStorage::new());
+/// MILLIS.store(1_000, Ordering::Relaxed); // wrapped past u32::MAX
+/// assert_eq!(logger.0.elapsed(), Duration::from_millis(1_500));
+/// logger.logdisp(StatusLevel::Info, "still counting");
+/// assert_eq!(strip_ansi(logger.1.as_str()), "I&:1.500s: still counting\n");
+/// ```
+pub struct TickClock<C: TickSource> {
+    start: u64,
+    source: core::marker::PhantomData<C>,
+}
+
+impl<C: TickSource> Clone for TickClock<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: TickSource> Copy for TickClock<C> {}
+
+impl<C: TickSource> TimeProvider for TickClock<C> {
+    fn now() -> Self {
+        TickClock {
+            start: C::ticks(),
+            source: core::marker::PhantomData,
+        }
+    }
+    fn elapsed(&self) -> core::time::Duration {
+        let mask = if C::BITS >= 64 {
+            u64::MAX
+        } else {
+            (1 << C::BITS) - 1
+        };
+        let ticks = C::ticks().wrapping_sub(self.start) & mask;
+        let nanos = ticks as u128 * 1_000_000_000 / C::TICKS_PER_SECOND as u128;
+        core::time::Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+    fn write(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let elapsed = self.elapsed();
+        write!(f, "{}.{:03}s:", elapsed.as_secs(), elapsed.subsec_millis())
+    }
+}
+
+impl TimeProvider for () {
+    fn now() -> Self {}
+    fn elapsed(&self) -> core::time::Duration {
+        core::time::Duration::ZERO
+    }
+    fn write(&self, _f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+/// Least severe level that is compiled in; the level methods (`log_info`, ...)
+/// for anything below it are empty.
+///
+/// Set with the `max-level-info`, `max-level-warning`, `max-level-error` or
+/// `max-level-critical` features, or at build time with the `CAND_MAX_LEVEL`
+/// environment variable (`ok`, `info`, `warning`, `error` or `critical`); the
+/// stricter of the two wins. Defaults to `Ok`, which keeps everything.
+///
+/// Every logger method drops messages below it, whatever the storage accepts:
+///
+/// ```
+/// use cand::{CaptureStorage, Logger, StatusLevel, STATIC_MAX_LEVEL};
+///
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// let levels = [StatusLevel::Ok, StatusLevel::Info, StatusLevel::Warning, StatusLevel::Error, StatusLevel::Critical];
+/// for level in levels {
+///     logger.logdisp(level, "plain");
+///     logger.log(level, "debug");
+///     logger.log_kv(level, "kv", &[("n", &1)]);
+///     logger.logdisp_long(level, "long");
+///     logger.log_hex(level, "hex", &[0xaa]);
+/// }
+/// let kept = levels.iter().filter(|level| level.severity() >= STATIC_MAX_LEVEL.severity());
+/// assert_eq!(capture.len(), kept.count() * 5);
+/// ```
+pub const STATIC_MAX_LEVEL: StatusLevel = {
+    let feature = if cfg!(feature = "max-level-critical") {
+        StatusLevel::Critical
+    } else if cfg!(feature = "max-level-error") {
+        StatusLevel::Error
+    } else if cfg!(feature = "max-level-warning") {
+        StatusLevel::Warning
+    } else if cfg!(feature = "max-level-info") {
+        StatusLevel::Info
+    } else {
+        StatusLevel::Ok
+    };
+    let env = match option_env!("CAND_MAX_LEVEL") {
+        None => StatusLevel::Ok,
+        Some(level) => match level.as_bytes() {
+            b"ok" => StatusLevel::Ok,
+            b"info" => StatusLevel::Info,
+            b"warning" => StatusLevel::Warning,
+            b"error" => StatusLevel::Error,
+            b"critical" => StatusLevel::Critical,
+            _ => panic!("CAND_MAX_LEVEL must be ok, info, warning, error or critical"),
+        },
+    };
+    if env.severity() > feature.severity() {
+        env
+    } else {
+        feature
+    }
+};
+
+/// Whether `level` is compiled in at all, as a constant: `level_enabled!(StatusLevel::Info)`
+/// is `false` when [`STATIC_MAX_LEVEL`] is above `Info`.
+///
+/// The level methods and the [`info!`]-style macros put their body behind this,
+/// so with e.g. the `max-level-warning` feature the format strings of `Info` and
+/// `Ok` calls are dropped at compile time and never reach the binary.
+///
+/// ```
+/// use cand::{level_enabled, StatusLevel};
+///
+/// assert!(level_enabled!(StatusLevel::Critical));
+/// ```
+#[macro_export]
+macro_rules! level_enabled {
+    ($level:expr) => {
+        const { $crate::StatusLevel::severity(&$level) >= $crate::STATIC_MAX_LEVEL.severity() }
+    };
+}
+
+macro_rules! impl_log_methods {
+    ($($method:ident => $level:expr),* $(,)?) => {
+        $(
+            pub fn $method(&mut self, args: impl Display) {
+                if level_enabled!($level) {
+                    self.logdisp($level, args);
+                }
+            }
+        )*
+    };
+    (shared: $($method:ident => $level:expr),* $(,)?) => {
+        $(
+            pub fn $method(&self, args: impl Display) {
+                if level_enabled!($level) {
+                    self.logdisp($level, args);
+                }
+            }
+        )*
+    };
+}
+
+/// Causes shown by [`ErrorChain`] before the rest is cut off.
+#[cfg(feature = "alloc")]
+const ERROR_CHAIN_DEPTH: usize = 8;
+
+/// An error followed by its [`source`](core::error::Error::source) chain, one
+/// indented `caused by:` line per cause, as logged by `try_get` and `try_run`.
+#[cfg(feature = "alloc")]
+struct ErrorChain<'a>(&'a (dyn core::error::Error + 'a));
+
+#[cfg(feature = "alloc")]
+impl Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        for _ in 0..ERROR_CHAIN_DEPTH {
+            let Some(cause) = source else {
+                return Ok(());
+            };
+            write!(f, "\n    caused by: {cause}")?;
+            source = cause.source();
+        }
+        if source.is_some() {
+            write!(f, "\n    caused by: …")?;
+        }
+        Ok(())
+    }
+}
+
+/// `attempt 2/5 failed: <error>`, logged by the `try_get_retry` methods.
+struct Attempt<E> {
+    number: u32,
+    attempts: u32,
+    err: E,
+}
+
+impl<E: Debug> Debug for Attempt<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "attempt {}/{} failed: {:?}",
+            self.number, self.attempts, self.err
+        )
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<E: uDebug> uDebug for Attempt<E> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str("attempt ")?;
+        uDisplay::fmt(&self.number, f)?;
+        f.write_str("/")?;
+        uDisplay::fmt(&self.attempts, f)?;
+        f.write_str(" failed: ")?;
+        uDebug::fmt(&self.err, f)
+    }
+}
+
+macro_rules! impl_try_get_retry {
+    ($error_bound:path $(, $(#[$attr:meta])*)?) => {
+        /// Calls `op` up to `attempts` times (at least once) and returns its first `Ok`.
+        ///
+        /// Failed attempts are logged at `Warning` as `attempt 2/5 failed: ...`; if
+        /// all fail, the last one is logged at `Error` and its error returned.
+        $($(#[$attr])*)?
+        pub fn try_get_retry<O, E: $error_bound>(
+            &mut self,
+            attempts: u32,
+            op: impl FnMut() -> Result<O, E>,
+        ) -> Result<O, E> {
+            self.try_get_retry_with(attempts, |_| {}, op)
+        }
+
+        /// Same as [`try_get_retry`](Self::try_get_retry), but calls `delay` with the
+        /// number of the failed attempt before the next one, e.g. a board's `delay_ms`
+        /// for backoff on no_std.
+        pub fn try_get_retry_with<O, E: $error_bound>(
+            &mut self,
+            attempts: u32,
+            mut delay: impl FnMut(u32),
+            mut op: impl FnMut() -> Result<O, E>,
+        ) -> Result<O, E> {
+            let attempts = attempts.max(1);
+            let mut number = 1;
+            loop {
+                match op() {
+                    Ok(value) => return Ok(value),
+                    Err(err) if number == attempts => {
+                        self.log(StatusLevel::Error, Attempt { number, attempts, err: &err });
+                        return Err(err);
+                    }
+                    Err(err) => {
+                        self.log(StatusLevel::Warning, Attempt { number, attempts, err });
+                        delay(number);
+                        number += 1;
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        /// Same as [`try_get_retry`](Self::try_get_retry), sleeping `delay` between attempts.
+        pub fn try_get_retry_sleep<O, E: $error_bound>(
+            &mut self,
+            attempts: u32,
+            delay: core::time::Duration,
+            op: impl FnMut() -> Result<O, E>,
+        ) -> Result<O, E> {
+            self.try_get_retry_with(attempts, |_| std::thread::sleep(delay), op)
+        }
+    };
+}
+
+macro_rules! impl_try_get_default {
+    ($error_bound:path $(, $(#[$attr:meta])*)?) => {
+        /// Returns the `Ok` value, or logs the error at `Warning` and returns `default`.
+        $($(#[$attr])*)?
+        pub fn try_get_default<O, E: $error_bound>(&mut self, result: Result<O, E>, default: O) -> O {
+            self.try_get_or_else(result, || default)
+        }
+
+        /// Returns the `Ok` value, or logs the error at `Warning` and returns what `f` produces.
+        pub fn try_get_or_else<O, E: $error_bound>(
+            &mut self,
+            result: Result<O, E>,
+            f: impl FnOnce() -> O,
+        ) -> O {
+            match result {
+                Ok(value) => value,
+                Err(err) => {
+                    self.log(StatusLevel::Warning, err);
+                    f()
+                }
+            }
+        }
+    };
+}
+
+macro_rules! impl_try_get {
+    ($error_bound:path, owned $(, $(#[$attr:meta])*)?) => {
+        #[cfg(feature = "std")]
+        #[cfg(feature = "ufmt")]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O>(
+            mut self, // Takes ownership
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.log(
+                        StatusLevel::Warning,
+                        UDebugStr(&ErrorChain(&*err).to_string()),
+                    );
+                    redirectfn(self);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg(not(feature = "ufmt"))]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O>(
+            mut self, // Takes ownership
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.logdisp(StatusLevel::Warning, ErrorChain(&*err));
+                    redirectfn(self);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        /// Same as [`try_get`](Self::try_get), named for what it does on error.
+        pub fn try_get_or_exit<O>(
+            self,
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            self.try_get(tryresult, redirectfn)
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Same as [`try_get`](Self::try_get), named for what it does on error.
+        pub fn try_get_or_exit<O, E: $error_bound>(
+            self,
+            tryresult: Result<O, E>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            self.try_get(tryresult, redirectfn)
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O, E: $error_bound>(
+            mut self, // Takes ownership
+            tryresult: Result<O, E>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.log(StatusLevel::Warning, err);
+                    redirectfn(self);
+                    loop {}
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg(feature = "ufmt")]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        $($(#[$attr])*)?
+        pub fn try_get_or<O, F>(
+            mut self, // Takes ownership
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.log(
+                        StatusLevel::Warning,
+                        UDebugStr(&ErrorChain(&*err).to_string()),
+                    );
+                    (fallback(&mut self), self)
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg(not(feature = "ufmt"))]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        $($(#[$attr])*)?
+        pub fn try_get_or<O, F>(
+            mut self, // Takes ownership
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.logdisp(StatusLevel::Warning, ErrorChain(&*err));
+                    (fallback(&mut self), self)
+                }
+            }
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        pub fn try_get_or<O, E: $error_bound, F>(
+            mut self, // Takes ownership
+            tryresult: Result<O, E>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            match tryresult {
+                Ok(x) => (x, self),
+                Err(err) => {
+                    self.log(StatusLevel::Warning, err);
+                    (fallback(&mut self), self)
+                }
+            }
+        }
+    };
+
+    ($error_bound:path, cloned) => {
+        #[cfg(feature = "std")]
+        #[cfg(not(feature = "ufmt"))]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O>(
+            &mut self, // Takes reference
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.logdisp(StatusLevel::Warning, ErrorChain(&*err));
+                    redirectfn(new_self);
+                    std::process::exit(1);
+                }
+            }
+        }
+        #[cfg(feature = "std")]
+        #[cfg(feature = "ufmt")]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O>(
+            &mut self, // Takes reference
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.log(
+                        StatusLevel::Warning,
+                        UDebugStr(&ErrorChain(&*err).to_string()),
+                    );
+                    redirectfn(new_self);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        /// Same as [`try_get`](Self::try_get), named for what it does on error.
+        pub fn try_get_or_exit<O>(
+            &mut self,
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            self.try_get(tryresult, redirectfn)
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Same as [`try_get`](Self::try_get), named for what it does on error.
+        pub fn try_get_or_exit<O, E: $error_bound>(
+            &mut self,
+            tryresult: Result<O, E>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            self.try_get(tryresult, redirectfn)
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Returns the `Ok` value, or logs the error, runs `redirectfn` and
+        /// terminates (`exit(1)` on std, an endless loop on no_std).
+        pub fn try_get<O, E: $error_bound>(
+            &mut self, // Takes reference
+            tryresult: Result<O, E>,
+            redirectfn: fn(Self) -> (),
+        ) -> (O, Self) {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.log(StatusLevel::Warning, err);
+                    redirectfn(new_self);
+                    loop {}
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg(not(feature = "ufmt"))]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        pub fn try_get_or<O, F>(
+            &mut self, // Takes reference
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.logdisp(StatusLevel::Warning, ErrorChain(&*err));
+                    (fallback(&mut new_self), new_self)
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg(feature = "ufmt")]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        pub fn try_get_or<O, F>(
+            &mut self, // Takes reference
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.log(
+                        StatusLevel::Warning,
+                        UDebugStr(&ErrorChain(&*err).to_string()),
+                    );
+                    (fallback(&mut new_self), new_self)
+                }
+            }
+        }
+
+        #[cfg(not(feature = "std"))]
+        /// Returns the `Ok` value, or logs the error and returns whatever `fallback` produces.
+        pub fn try_get_or<O, E: $error_bound, F>(
+            &mut self, // Takes reference
+            tryresult: Result<O, E>,
+            fallback: F,
+        ) -> (O, Self)
+        where
+            F: FnOnce(&mut Self) -> O,
+        {
+            let mut new_self = self.clone();
+            match tryresult {
+                Ok(x) => (x, new_self),
+                Err(err) => {
+                    new_self.log(StatusLevel::Warning, err);
+                    (fallback(&mut new_self), new_self)
+                }
+            }
+        }
+    };
+}
+
+#[derive(Clone)]
+pub struct MultiLogger<T: TimeProvider + Clone, S: StorageProvider + Clone>(pub T, pub S);
+
+pub struct Logger<T: TimeProvider, S: StorageProvider>(pub T, pub S);
+
+#[cfg(feature = "std")]
+impl Logger<(), ()> {
+    /// Stdout without timestamps, same as `Logger((), ())`.
+    pub fn stdout() -> Self {
+        Logger((), ())
+    }
+}
+
+/// Same as [`Logger::stdout`], so structs holding a logger can derive `Default`:
+///
+/// ```
+/// use cand::Logger;
+///
+/// #[derive(Default)]
+/// struct Service {
+///     logger: Logger<(), ()>,
+/// }
+///
+/// Service::default().logger.log_info("started");
+/// ```
+#[cfg(feature = "std")]
+impl Default for Logger<(), ()> {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Logger<Instant, ()> {
+    /// Stdout with the time since creation, same as `Logger(Instant::now(), ())`.
+    pub fn stdout_timed() -> Self {
+        Logger(Instant::now(), ())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Logger<(), IoWriter<std::io::Stderr>> {
+    /// Stderr without timestamps, same as `Logger((), IoWriter::stderr())`.
+    pub fn stderr() -> Self {
+        Logger((), IoWriter::stderr())
+    }
+}
+
+impl Logger<(), NullStorage> {
+    /// Discards everything, see [`NullStorage`].
+    pub fn null() -> Self {
+        Logger((), NullStorage)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MultiLogger<(), ()> {
+    /// Stdout without timestamps, same as `MultiLogger((), ())`.
+    pub fn stdout() -> Self {
+        MultiLogger((), ())
+    }
+}
+
+/// Same as [`MultiLogger::stdout`].
+#[cfg(feature = "std")]
+impl Default for MultiLogger<(), ()> {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+#[cfg(feature = "std")]
+impl MultiLogger<Instant, ()> {
+    /// Stdout with the time since creation, same as `MultiLogger(Instant::now(), ())`.
+    pub fn stdout_timed() -> Self {
+        MultiLogger(Instant::now(), ())
+    }
+}
+
+impl MultiLogger<(), NullStorage> {
+    /// Discards everything, see [`NullStorage`].
+    pub fn null() -> Self {
+        MultiLogger((), NullStorage)
+    }
+}
+
+impl<T: TimeProvider + Clone, S: StorageProvider + Clone> MultiLogger<T, S>
+where
+    Self: Clone,
+{
+    /// Same as `MultiLogger(time, storage)`.
+    pub const fn new(time: T, storage: S) -> Self {
+        MultiLogger(time, storage)
+    }
+
+    /// Clones the logger into a [`MultiScopedLogger`] that tags every message
+    /// with `[scope] `; scopes nest as `wifi/dhcp`.
+    pub fn scoped<'a>(&self, scope: &'a str) -> MultiScopedLogger<'a, T, S> {
+        MultiScopedLogger {
+            logger: self.clone(),
+            scope: Scope {
+                parent: None,
+                name: scope,
+            },
+        }
+    }
+
+    /// Wraps the storage in a [`LevelFilter`] so messages below `min_level`
+    /// are dropped before they are formatted.
+    pub fn with_min_level(self, min_level: StatusLevel) -> MultiLogger<T, LevelFilter<S>> {
+        MultiLogger(self.0, LevelFilter::new(self.1, min_level))
+    }
+
+    /// Wraps the storage in a [`Filtered`] so only messages `filter` accepts are written.
+    pub fn with_filter<F: LogFilter + Clone>(self, filter: F) -> MultiLogger<T, Filtered<S, F>> {
+        MultiLogger(self.0, Filtered::new(self.1, filter))
+    }
+
+    pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{:?}", args),
+        ));
+    }
+
+    pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{}", args),
+        ));
+    }
+
+    /// Logs `args` followed by a dimmed `(file:line)`, see [`Logger::log_at`].
+    pub fn log_at(&mut self, level: StatusLevel, location: &SourceLocation, args: impl Display) {
+        self.logdisp(
+            level,
+            Located {
+                message: args,
+                location,
+            },
+        );
+    }
+
+    /// Logs a horizontal rule to break up long output.
+    pub fn log_separator(&mut self, level: StatusLevel) {
+        self.logdisp(level, SEPARATOR);
+    }
+
+    /// Logs `title` centered in a horizontal rule.
+    pub fn log_header(&mut self, level: StatusLevel, title: &str) {
+        self.logdisp(level, Header(title));
+    }
+
+    /// Logs `bytes` as hex after `label`, e.g. `label: aa bb cc`, without allocating.
+    pub fn log_bytes(&mut self, level: StatusLevel, label: &str, bytes: &[u8]) {
+        let hex = HexBytes {
+            bytes,
+            compact: false,
+        };
+        self.logdisp(level, format_args!("{label}: {hex}"));
+    }
+
+    /// Like [`log_bytes`](Self::log_bytes) without the spaces, e.g. `label: aabbcc`.
+    pub fn log_bytes_compact(&mut self, level: StatusLevel, label: &str, bytes: &[u8]) {
+        let hex = HexBytes {
+            bytes,
+            compact: true,
+        };
+        self.logdisp(level, format_args!("{label}: {hex}"));
+    }
+
+    /// Logs a canonical hex dump of `data`, see [`Logger::log_hex`].
+    pub fn log_hex(&mut self, level: StatusLevel, label: &str, data: &[u8]) {
+        self.log_hex_max(level, label, data, HEX_DUMP_MAX);
+    }
+
+    /// Like [`log_hex`](Self::log_hex), showing at most `max` bytes.
+    pub fn log_hex_max(&mut self, level: StatusLevel, label: &str, data: &[u8], max: usize) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let indent = core::cell::Cell::new(0);
+        let time = MeasuredTime {
+            time: &TimeFormatter(&self.0),
+            indent: &indent,
+        };
+        let dump = HexDump {
+            label,
+            bytes: data,
+            indent: &indent,
+            max,
+        };
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{dump}"),
+        ));
+    }
+
+    /// Logs `value` on the first and then every `rate`th call, counting in
+    /// `counter`, e.g. `sample_at_rate(&mut samples, 100, StatusLevel::Info, reading)`
+    /// in a 10 kHz loop. The caller owns the counter, so this needs no atomics.
+    pub fn sample_at_rate(
+        &mut self,
+        counter: &mut u32,
+        rate: u32,
+        level: StatusLevel,
+        value: impl Debug,
+    ) {
+        let due = counter.is_multiple_of(rate.max(1));
+        *counter = counter.wrapping_add(1);
+        if due {
+            self.log(level, value);
+        }
+    }
+
+    /// Does nothing if `condition` holds. Otherwise logs `"assertion failed: message"`
+    /// at `Critical` level, flushes, and panics on std or spins forever on no_std.
+    #[track_caller]
+    pub fn log_assert(&mut self, condition: bool, message: impl Display) {
+        if !condition {
+            self.logdisp(
+                StatusLevel::Critical,
+                format_args!("assertion failed: {message}"),
+            );
+            self.1.flush();
+            __assert_failed(message);
+        }
+    }
+
+    /// Logs `err` at `Error` level and returns it as `Err`, so `?` can pass it
+    /// on: `logger.log_err_return(err)?;`.
+    pub fn log_err_return<E: Debug + Display>(
+        &mut self,
+        err: E,
+    ) -> Result<core::convert::Infallible, E> {
+        self.logdisp(StatusLevel::Error, &err);
+        Err(err)
+    }
+
+    /// Flushes the storage, see [`StorageProvider::flush`].
+    pub fn flush(&mut self) {
+        self.1.flush();
+    }
+
+    /// Replaces the storage and returns the old one, e.g. to move from a RAM
+    /// buffer used during startup to the UART once it is up.
+    pub fn swap_storage(&mut self, storage: S) -> S {
+        core::mem::replace(&mut self.1, storage)
+    }
+
+    /// Takes the logger apart into its time provider and storage, e.g. to
+    /// flush or inspect the storage before it is dropped.
+    pub fn into_parts(self) -> (T, S) {
+        (self.0, self.1)
+    }
+
+    /// Turns this into a plain [`Logger`] with the same time provider and
+    /// storage, for code that no longer needs to clone it.
+    pub fn into_logger(self) -> Logger<T, S> {
+        Logger(self.0, self.1)
+    }
+
+    /// Runs `f`, logs `"label: <duration>"` at `level` and returns what `f` returned.
+    pub fn measure<R>(&mut self, level: StatusLevel, label: &str, f: impl FnOnce() -> R) -> R {
+        let start = self.0.elapsed();
+        let result = f();
+        let took = self.0.elapsed().saturating_sub(start);
+        self.logdisp(level, format_args!("{}: {}", label, HumanDuration(took)));
+        result
+    }
+
+    /// Logs `label [########··········] 42%` at [`Info`](StatusLevel::Info).
+    ///
+    /// When printing to a terminal through `()`, the bar is redrawn in place and
+    /// ends its line once `current` reaches `total` or something else is logged;
+    /// elsewhere every call is a new line.
+    pub fn log_progress(&mut self, current: u64, total: u64, label: &str) {
+        if !level_enabled!(StatusLevel::Info) || !self.1.enabled(&StatusLevel::Info) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        let progress = Progress {
+            current,
+            total,
+            label,
+        };
+        self.1.write_record(&Record {
+            progress: if current < total {
+                ProgressLine::Running
+            } else {
+                ProgressLine::Done
+            },
+            ..Record::new(
+                StatusLevel::Info,
+                self.0.elapsed(),
+                &time,
+                format_args!("{progress}"),
+            )
+        });
+    }
+
+    /// Logs `message` only the first time `key` is used in this program run, by
+    /// any logger, e.g. `log_once(StatusLevel::Warning, "legacy-baud", ...)`.
+    ///
+    /// Unlike [`log_once!`], which remembers its call site, the key can be shared
+    /// between call sites. Keys are kept in a set under std; without std the first
+    /// 64 distinct keys are remembered and later ones are always logged.
+    pub fn log_once(&mut self, level: StatusLevel, key: impl Into<OnceKey>, message: impl Display) {
+        if first_time(key.into()) {
+            self.logdisp(level, message);
+        }
+    }
+
+    /// Runs `f`; if it panics, logs `"name panicked: <message>"` at `Critical` and
+    /// returns `None` instead of unwinding further.
+    ///
+    /// The panic hook is silenced for this thread while `f` runs, so the panic is
+    /// not printed twice; panics on other threads still reach the installed hook.
+    #[cfg(feature = "std")]
+    pub fn catch<R>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce() -> R + std::panic::UnwindSafe,
+    ) -> Option<R> {
+        match catch_quietly(f) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                self.logdisp(
+                    StatusLevel::Critical,
+                    format_args!("{} panicked: {}", name, payload_to_str(&*payload)),
+                );
+                None
+            }
+        }
+    }
+
+    /// Logs `message` followed by `key=value` pairs, e.g.
+    /// `log_kv(StatusLevel::Warning, "i2c timeout", &[("bus", &0u8), ("addr", &0x48u8)])`.
+    ///
+    /// Structured storages such as [`JsonStorage`] receive the pairs as separate fields.
+    pub fn log_kv(&mut self, level: StatusLevel, message: impl Display, kv: &[(&str, &dyn Debug)]) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            kv,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{}", message))
+        });
+    }
+
+    /// Same as [`log`](Self::log), but `f` only runs when `level` is enabled.
+    pub fn log_lazy<D: Debug>(&mut self, level: StatusLevel, f: impl FnOnce() -> D) {
+        if level.severity() >= STATIC_MAX_LEVEL.severity() && self.1.enabled(&level) {
+            self.log(level, f());
+        }
+    }
+
+    /// Same as [`logdisp`](Self::logdisp), but `f` only runs when `level` is enabled.
+    pub fn log_lazy_disp<D: Display>(&mut self, level: StatusLevel, f: impl FnOnce() -> D) {
+        if level.severity() >= STATIC_MAX_LEVEL.severity() && self.1.enabled(&level) {
+            self.logdisp(level, f());
+        }
+    }
+
+    /// Logs only when `condition` is true.
+    pub fn log_if(&mut self, condition: bool, level: StatusLevel, args: impl Display) {
+        if condition {
+            self.logdisp(level, args);
+        }
+    }
+
+    /// Like [`log_if`](Self::log_if), but `f` only runs when the message is written.
+    pub fn log_if_lazy<D: Display>(
+        &mut self,
+        condition: bool,
+        level: StatusLevel,
+        f: impl FnOnce() -> D,
+    ) {
+        if condition {
+            self.log_lazy_disp(level, f);
+        }
+    }
+
+    /// Same as [`log`](Self::log) but prefixes the line with the full level name.
+    pub fn log_long(&mut self, level: StatusLevel, args: impl Debug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            long: true,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{:?}", args))
+        });
+    }
+
+    /// Same as [`logdisp`](Self::logdisp) but prefixes the line with the full level name.
+    pub fn logdisp_long(&mut self, level: StatusLevel, args: impl Display) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            long: true,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{}", args))
+        });
+    }
+
+    impl_log_methods! {
+        log_err => StatusLevel::Error,
+        log_ok => StatusLevel::Ok,
+        log_warn => StatusLevel::Warning,
+        log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+        if let Err(err) = tryresult {
+            self.logdisp(StatusLevel::Error, ErrorChain(&*err));
+        }
+    }
+    #[cfg(feature = "alloc")]
+    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.logdisp(StatusLevel::Error, ErrorChain(&*err)),
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run<O, E: core::fmt::Debug>(&mut self, tryresult: Result<O, E>) {
+        if let Err(err) = tryresult {
+            self.log(StatusLevel::Error, err);
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run_get<O, E: core::fmt::Debug, F>(&mut self, tryresult: Result<O, E>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.log(StatusLevel::Error, err),
+        }
+    }
+
+    impl_try_get_default!(core::fmt::Debug);
+
+    impl_try_get_retry!(core::fmt::Debug);
+
+    impl_try_get!(core::fmt::Debug, cloned);
+}
+
+impl<T: TimeProvider, S: StorageProvider> Logger<T, S> {
+    /// Same as `Logger(time, storage)`.
+    pub const fn new(time: T, storage: S) -> Self {
+        Logger(time, storage)
+    }
+
+    /// Wraps the storage in a [`LevelFilter`] so messages below `min_level`
+    /// are dropped before they are formatted.
+    pub fn with_min_level(self, min_level: StatusLevel) -> Logger<T, LevelFilter<S>> {
+        Logger(self.0, LevelFilter::new(self.1, min_level))
+    }
+
+    /// Wraps the storage in a [`Filtered`] so only messages `filter` accepts are written.
+    pub fn with_filter<F: LogFilter>(self, filter: F) -> Logger<T, Filtered<S, F>> {
+        Logger(self.0, Filtered::new(self.1, filter))
+    }
+
+    /// Same as [`scoped`](Self::scoped), e.g. for a subsystem such as `"WiFi"`.
+    pub fn with_prefix<'a>(&'a mut self, prefix: &'a str) -> ScopedLogger<'a, T, S> {
+        self.scoped(prefix)
+    }
+
+    /// Borrows the logger as a [`ScopedLogger`] that tags every message with
+    /// `[scope] `; scopes nest as `wifi/dhcp`.
+    pub fn scoped<'a>(&'a mut self, scope: &'a str) -> ScopedLogger<'a, T, S> {
+        ScopedLogger {
+            logger: self,
+            scope: Scope {
+                parent: None,
+                name: scope,
+            },
+        }
+    }
+
+    /// Borrows the logger as a [`ContextLogger`] that starts every message with
+    /// the `key=value` pairs of `context`, e.g. a request ID.
+    pub fn with_context<'a>(
+        &'a mut self,
+        context: &'a [(&'a str, &'a str)],
+    ) -> ContextLogger<'a, T, S> {
+        ContextLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    /// Logs `"label: <duration>"` at `level` when the returned guard drops.
+    ///
+    /// The guard derefs to the logger, so it can still be used inside the block.
+    pub fn timed<'a>(&'a mut self, level: StatusLevel, label: &'a str) -> TimedGuard<'a, T, S> {
+        let start = self.0.elapsed();
+        TimedGuard {
+            logger: self,
+            level,
+            label,
+            start,
+            warn_after: None,
+        }
+    }
+
+    pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{:?}", args),
+        ));
+    }
+
+    pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{}", args),
+        ));
+    }
+
+    /// Logs `args` followed by a dimmed `(file:line)`. The [`log!`] macro fills
+    /// in the call site:
+    ///
+    /// ```
+    /// use cand::{strip_ansi, Logger, VecStorage};
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// cand::log!(logger, Critical, "retry {}", 3);
+    /// let expected = format!("C&: retry 3 ({}:{})\n", file!(), line!() - 1);
+    /// assert_eq!(strip_ansi(logger.1.as_str()), expected);
+    /// ```
+    pub fn log_at(&mut self, level: StatusLevel, location: &SourceLocation, args: impl Display) {
+        self.logdisp(
+            level,
+            Located {
+                message: args,
+                location,
+            },
+        );
+    }
+
+    /// Logs a horizontal rule to break up long output.
+    pub fn log_separator(&mut self, level: StatusLevel) {
+        self.logdisp(level, SEPARATOR);
+    }
+
+    /// Logs `title` centered in a horizontal rule.
+    pub fn log_header(&mut self, level: StatusLevel, title: &str) {
+        self.logdisp(level, Header(title));
+    }
+
+    /// Logs `bytes` as hex after `label`, e.g. `label: aa bb cc`, without allocating.
+    pub fn log_bytes(&mut self, level: StatusLevel, label: &str, bytes: &[u8]) {
+        let hex = HexBytes {
+            bytes,
+            compact: false,
+        };
+        self.logdisp(level, format_args!("{label}: {hex}"));
+    }
+
+    /// Like [`log_bytes`](Self::log_bytes) without the spaces, e.g. `label: aabbcc`.
+    pub fn log_bytes_compact(&mut self, level: StatusLevel, label: &str, bytes: &[u8]) {
+        let hex = HexBytes {
+            bytes,
+            compact: true,
+        };
+        self.logdisp(level, format_args!("{label}: {hex}"));
+    }
+
+    /// Logs a canonical hex dump of `data`: 16 bytes per line with the offset
+    /// and an ASCII column, lines indented under the label. Without allocating;
+    /// after [`HEX_DUMP_MAX`] bytes the rest is summarized as `… (N more bytes)`.
+    ///
+    /// ```
+    /// use cand::{strip_ansi, Logger, StatusLevel, VecStorage};
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// let mut dump = |label: &str, data: &[u8], max: usize| {
+    ///     logger.1.clear();
+    ///     logger.log_hex_max(StatusLevel::Info, label, data, max);
+    ///     strip_ansi(logger.1.as_str())
+    /// };
+    ///
+    /// assert_eq!(dump("empty", b"", 256), "I&: empty (0 bytes)\n");
+    /// assert_eq!(
+    ///     dump("frame", b"Hello, world!\r\n\x00", 256),
+    ///     "I&: frame (16 bytes)\n    \
+    ///      00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0d 0a 00  |Hello, world!...|\n"
+    /// );
+    /// assert_eq!(
+    ///     dump("frame", b"0123456789abcdef\xff", 256),
+    ///     "I&: frame (17 bytes)\n    \
+    ///      00000000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|\n    \
+    ///      00000010  ff                                                |.|\n"
+    /// );
+    /// assert_eq!(
+    ///     dump("spi", &[0x7f, 0x80, b'~', 0x1b], 2),
+    ///     "I&: spi (4 bytes)\n    \
+    ///      00000000  7f 80                                             |..|\n    \
+    ///      … (2 more bytes)\n"
+    /// );
+    /// ```
+    pub fn log_hex(&mut self, level: StatusLevel, label: &str, data: &[u8]) {
+        self.log_hex_max(level, label, data, HEX_DUMP_MAX);
+    }
+
+    /// Like [`log_hex`](Self::log_hex), showing at most `max` bytes.
+    pub fn log_hex_max(&mut self, level: StatusLevel, label: &str, data: &[u8], max: usize) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let indent = core::cell::Cell::new(0);
+        let time = MeasuredTime {
+            time: &TimeFormatter(&self.0),
+            indent: &indent,
+        };
+        let dump = HexDump {
+            label,
+            bytes: data,
+            indent: &indent,
+            max,
+        };
+        self.1.write_record(&Record::new(
+            level,
+            self.0.elapsed(),
+            &time,
+            format_args!("{dump}"),
+        ));
+    }
+
+    /// Logs `value` on the first and then every `rate`th call, counting in
+    /// `counter`, e.g. `sample_at_rate(&mut samples, 100, StatusLevel::Info, reading)`
+    /// in a 10 kHz loop. The caller owns the counter, so this needs no atomics.
+    ///
+    /// ```
+    /// use cand::{CaptureStorage, Logger, StatusLevel};
+    ///
+    /// let capture = CaptureStorage::new();
+    /// let mut logger = Logger((), capture.clone());
+    /// let mut samples = 0;
+    /// for reading in 0..250u32 {
+    ///     logger.sample_at_rate(&mut samples, 100, StatusLevel::Info, reading);
+    /// }
+    /// let texts: Vec<_> = capture.messages().into_iter().map(|(_, text)| text).collect();
+    /// assert_eq!(texts, ["0", "100", "200"]);
+    /// ```
+    pub fn sample_at_rate(
+        &mut self,
+        counter: &mut u32,
+        rate: u32,
+        level: StatusLevel,
+        value: impl Debug,
+    ) {
+        let due = counter.is_multiple_of(rate.max(1));
+        *counter = counter.wrapping_add(1);
+        if due {
+            self.log(level, value);
+        }
+    }
+
+    /// Does nothing if `condition` holds. Otherwise logs `"assertion failed: message"`
+    /// at `Critical` level, flushes, and panics on std or spins forever on no_std.
+    #[track_caller]
+    pub fn log_assert(&mut self, condition: bool, message: impl Display) {
+        if !condition {
+            self.logdisp(
+                StatusLevel::Critical,
+                format_args!("assertion failed: {message}"),
+            );
+            self.1.flush();
+            __assert_failed(message);
+        }
+    }
+
+    /// Logs `err` at `Error` level and returns it as `Err`, so `?` can pass it on:
+    ///
+    /// ```
+    /// use cand::{strip_ansi, Logger, VecStorage};
+    ///
+    /// fn parse(logger: &mut Logger<(), VecStorage>, s: &str) -> Result<u8, core::num::ParseIntError> {
+    ///     match s.parse() {
+    ///         Ok(value) => Ok(value),
+    ///         Err(err) => match logger.log_err_return(err)? {},
+    ///     }
+    /// }
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// assert!(parse(&mut logger, "300").is_err());
+    /// assert!(strip_ansi(logger.1.as_str()).contains("number too large"));
+    /// ```
+    pub fn log_err_return<E: Debug + Display>(
+        &mut self,
+        err: E,
+    ) -> Result<core::convert::Infallible, E> {
+        self.logdisp(StatusLevel::Error, &err);
+        Err(err)
+    }
+
+    /// Flushes the storage, see [`StorageProvider::flush`].
+    pub fn flush(&mut self) {
+        self.1.flush();
+    }
+
+    /// Replaces the storage and returns the old one, e.g. to move from a RAM
+    /// buffer used during startup to the UART once it is up.
+    pub fn swap_storage(&mut self, storage: S) -> S {
+        core::mem::replace(&mut self.1, storage)
+    }
+
+    /// Takes the logger apart into its time provider and storage, e.g. to
+    /// flush or inspect the storage before it is dropped.
+    pub fn into_parts(self) -> (T, S) {
+        (self.0, self.1)
+    }
+
+    /// Runs `f`, logs `"label: <duration>"` at `level` and returns what `f` returned.
+    pub fn measure<R>(&mut self, level: StatusLevel, label: &str, f: impl FnOnce() -> R) -> R {
+        let start = self.0.elapsed();
+        let result = f();
+        let took = self.0.elapsed().saturating_sub(start);
+        self.logdisp(level, format_args!("{}: {}", label, HumanDuration(took)));
+        result
+    }
+
+    /// Logs `label [########··········] 42%` at [`Info`](StatusLevel::Info).
+    ///
+    /// When printing to a terminal through `()`, the bar is redrawn in place and
+    /// ends its line once `current` reaches `total` or something else is logged;
+    /// elsewhere every call is a new line.
+    ///
+    /// ```
+    /// use cand::{Logger, StatusLevel, VecStorage};
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// logger.log_progress(42, 100, "flashing");
+    /// let drawn = logger.1.as_str().contains("flashing [########············] 42%");
+    /// assert_eq!(drawn, cand::level_enabled!(StatusLevel::Info));
+    /// ```
+    pub fn log_progress(&mut self, current: u64, total: u64, label: &str) {
+        if !level_enabled!(StatusLevel::Info) || !self.1.enabled(&StatusLevel::Info) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        let progress = Progress {
+            current,
+            total,
+            label,
+        };
+        self.1.write_record(&Record {
+            progress: if current < total {
+                ProgressLine::Running
+            } else {
+                ProgressLine::Done
+            },
+            ..Record::new(
+                StatusLevel::Info,
+                self.0.elapsed(),
+                &time,
+                format_args!("{progress}"),
+            )
+        });
+    }
+
+    /// Logs `message` only the first time `key` is used in this program run, by
+    /// any logger, e.g. `log_once(StatusLevel::Warning, "legacy-baud", ...)`.
+    ///
+    /// Unlike [`log_once!`], which remembers its call site, the key can be shared
+    /// between call sites. Keys are kept in a set under std; without std the first
+    /// 64 distinct keys are remembered and later ones are always logged.
+    ///
+    /// ```
+    /// use cand::{CaptureStorage, Logger, StatusLevel};
+    ///
+    /// let capture = CaptureStorage::new();
+    /// let mut logger = Logger((), capture.clone());
+    /// for _ in 0..3 {
+    ///     logger.log_once(StatusLevel::Warning, "legacy-baud", "`baud` is deprecated, use `uart.baud`");
+    ///     logger.log_once(StatusLevel::Info, 7u64, "sensor 7 online");
+    /// }
+    /// assert_eq!(capture.len(), 2);
+    /// ```
+    pub fn log_once(&mut self, level: StatusLevel, key: impl Into<OnceKey>, message: impl Display) {
+        if first_time(key.into()) {
+            self.logdisp(level, message);
+        }
+    }
+
+    /// Runs `f`; if it panics, logs `"name panicked: <message>"` at `Critical` and
+    /// returns `None` instead of unwinding further.
+    ///
+    /// The panic hook stays quiet for this thread while `f` runs, so the panic is
+    /// not printed twice; panics on other threads still reach the installed hook.
+    /// Concurrent `catch` calls on several threads are fine.
+    ///
+    /// ```
+    /// use cand::{CaptureStorage, Logger, StatusLevel};
+    ///
+    /// let capture = CaptureStorage::new();
+    /// let mut logger = Logger((), capture.clone());
+    /// let plugins: [(&str, fn() -> u32); 2] = [("ok", || 1), ("broken", || panic!("index out of range"))];
+    /// let results: Vec<_> = plugins.iter().map(|(name, run)| logger.catch(name, run)).collect();
+    /// assert_eq!(results, [Some(1), None]);
+    /// assert!(capture.contains(StatusLevel::Critical, "broken panicked: index out of range"));
+    ///
+    /// logger.logdisp(StatusLevel::Info, "still logging");
+    /// assert!(capture.contains(StatusLevel::Info, "still logging"));
+    /// ```
+    ///
+    /// Two threads catching at the same time, then a panic outside `catch`:
+    ///
+    /// ```standalone_crate
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::{Arc, Barrier};
+    /// use cand::{CaptureStorage, Logger, StatusLevel};
+    ///
+    /// static HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+    /// std::panic::set_hook(Box::new(|_| {
+    ///     HOOK_CALLS.fetch_add(1, Ordering::SeqCst);
+    /// }));
+    ///
+    /// let capture = CaptureStorage::new();
+    /// for _ in 0..20 {
+    ///     let barrier = Arc::new(Barrier::new(2));
+    ///     let threads: Vec<_> = (0..2)
+    ///         .map(|n| {
+    ///             let mut logger = Logger((), capture.clone());
+    ///             let barrier = barrier.clone();
+    ///             std::thread::spawn(move || {
+    ///                 logger.catch(&format!("plugin {n}"), move || {
+    ///                     barrier.wait();
+    ///                     panic!("boom")
+    ///                 })
+    ///             })
+    ///         })
+    ///         .collect();
+    ///     for thread in threads {
+    ///         assert_eq!(thread.join().unwrap(), None::<()>);
+    ///     }
+    /// }
+    /// assert_eq!(HOOK_CALLS.load(Ordering::SeqCst), 0);
+    /// assert_eq!(capture.len(), 40);
+    /// assert!(capture.contains(StatusLevel::Critical, "plugin 1 panicked: boom"));
+    ///
+    /// assert!(std::thread::spawn(|| panic!("uncaught")).join().is_err());
+    /// assert_eq!(HOOK_CALLS.load(Ordering::SeqCst), 1);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn catch<R>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce() -> R + std::panic::UnwindSafe,
+    ) -> Option<R> {
+        match catch_quietly(f) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                self.logdisp(
+                    StatusLevel::Critical,
+                    format_args!("{} panicked: {}", name, payload_to_str(&*payload)),
+                );
+                None
+            }
+        }
+    }
+
+    /// Logs `message` followed by `key=value` pairs, e.g.
+    /// `log_kv(StatusLevel::Warning, "i2c timeout", &[("bus", &0u8), ("addr", &0x48u8)])`.
+    ///
+    /// Structured storages such as [`JsonStorage`] receive the pairs as separate fields.
+    /// On the console they follow the message in call order, values in their
+    /// `Debug` form, inside the level color:
+    ///
+    /// ```
+    /// use cand::{set_color_mode, ColorMode, ColorTheme, Logger, MultiLogger, StatusLevel, VecStorage};
+    ///
+    /// set_color_mode(ColorMode::Always);
+    /// let kv: [(&str, &dyn core::fmt::Debug); 3] = [("addr", &0x48u8), ("dev", &"i2c\"1\""), ("bus", &0u8)];
+    /// let mut logger = Logger((), VecStorage::new());
+    /// logger.log_kv(StatusLevel::Warning, "i2c timeout", &kv);
+    /// let warning = ColorTheme::DEFAULT.color(StatusLevel::Warning);
+    /// let expected = format!("{warning}i2c timeout addr=72 dev=\"i2c\\\"1\\\"\" bus=0\x1b[0m\n");
+    /// assert!(logger.1.as_str().ends_with(&expected));
+    ///
+    /// let mut multi = MultiLogger((), VecStorage::new());
+    /// multi.log_kv(StatusLevel::Warning, "i2c timeout", &kv);
+    /// assert_eq!(multi.1.as_str(), logger.1.as_str());
+    /// ```
+    pub fn log_kv(&mut self, level: StatusLevel, message: impl Display, kv: &[(&str, &dyn Debug)]) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            kv,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{}", message))
+        });
+    }
+
+    /// Same as [`log`](Self::log), but `f` only runs when `level` is enabled.
+    pub fn log_lazy<D: Debug>(&mut self, level: StatusLevel, f: impl FnOnce() -> D) {
+        if level.severity() >= STATIC_MAX_LEVEL.severity() && self.1.enabled(&level) {
+            self.log(level, f());
+        }
+    }
+
+    /// Same as [`logdisp`](Self::logdisp), but `f` only runs when `level` is enabled.
+    pub fn log_lazy_disp<D: Display>(&mut self, level: StatusLevel, f: impl FnOnce() -> D) {
+        if level.severity() >= STATIC_MAX_LEVEL.severity() && self.1.enabled(&level) {
+            self.logdisp(level, f());
+        }
+    }
+
+    /// Logs only when `condition` is true.
+    pub fn log_if(&mut self, condition: bool, level: StatusLevel, args: impl Display) {
+        if condition {
+            self.logdisp(level, args);
+        }
+    }
+
+    /// Like [`log_if`](Self::log_if), but `f` only runs when the message is written.
+    pub fn log_if_lazy<D: Display>(
+        &mut self,
+        condition: bool,
+        level: StatusLevel,
+        f: impl FnOnce() -> D,
+    ) {
+        if condition {
+            self.log_lazy_disp(level, f);
+        }
+    }
+
+    /// Same as [`log`](Self::log) but prefixes the line with the full level name.
+    pub fn log_long(&mut self, level: StatusLevel, args: impl Debug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            long: true,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{:?}", args))
+        });
+    }
+
+    /// Same as [`logdisp`](Self::logdisp) but prefixes the line with the full level name.
+    pub fn logdisp_long(&mut self, level: StatusLevel, args: impl Display) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() || !self.1.enabled(&level) {
+            return;
+        }
+        let time = TimeFormatter(&self.0);
+        self.1.write_record(&Record {
+            long: true,
+            ..Record::new(level, self.0.elapsed(), &time, format_args!("{}", args))
+        });
+    }
+
+    impl_log_methods! {
+        log_err => StatusLevel::Error,
+        log_ok => StatusLevel::Ok,
+        log_warn => StatusLevel::Warning,
+        log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
+    }
+
+    /// Logs the error at `Error` level if there is one, with each of its
+    /// [`source`](core::error::Error::source)s on a `caused by:` line:
+    ///
+    /// ```
+    /// use std::{error::Error, fmt};
+    /// use cand::{strip_ansi, Logger, VecStorage};
+    ///
+    /// #[derive(Debug)]
+    /// struct Failed(&'static str, Option<Box<dyn Error>>);
+    ///
+    /// impl fmt::Display for Failed {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         f.write_str(self.0)
+    ///     }
+    /// }
+    ///
+    /// impl Error for Failed {
+    ///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+    ///         self.1.as_deref()
+    ///     }
+    /// }
+    ///
+    /// let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+    /// let read = Failed("reading config.toml", Some(Box::new(io)));
+    /// let start = Failed("startup failed", Some(Box::new(read)));
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// logger.try_run::<()>(Err(Box::new(start)));
+    /// assert_eq!(
+    ///     strip_ansi(logger.1.as_str()),
+    ///     "E&: startup failed\n    caused by: reading config.toml\n    caused by: no such file\n"
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+        if let Err(err) = tryresult {
+            self.logdisp(StatusLevel::Error, ErrorChain(&*err));
+        }
+    }
+
+    /// Passes the `Ok` value to `value`, or logs the error at `Error` without calling it.
+    ///
+    /// ```
+    /// use cand::{strip_ansi, Logger, VecStorage};
+    ///
+    /// let mut logger = Logger((), VecStorage::new());
+    /// let mut calls = Vec::new();
+    /// logger.try_run_get("42".parse::<u8>().map_err(Into::into), |port| calls.push(port));
+    /// logger.try_run_get("x".parse::<u8>().map_err(Into::into), |port| calls.push(port));
+    /// assert_eq!(calls, [42]);
+    /// assert_eq!(strip_ansi(logger.1.as_str()), "E&: invalid digit found in string\n");
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.logdisp(StatusLevel::Error, ErrorChain(&*err)),
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run<O, E: core::fmt::Debug>(&mut self, tryresult: Result<O, E>) {
+        if let Err(err) = tryresult {
+            self.log(StatusLevel::Error, err);
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run_get<O, E: core::fmt::Debug, F>(&mut self, tryresult: Result<O, E>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.log(StatusLevel::Error, err),
+        }
+    }
+
+    impl_try_get_default!(
+        core::fmt::Debug,
+        ///
+        /// ```
+        /// use cand::{strip_ansi, Logger, VecStorage};
+        ///
+        /// let mut logger = Logger((), VecStorage::new());
+        /// assert_eq!(logger.try_get_default("8080".parse::<u16>(), 80), 8080);
+        /// assert!(logger.1.as_str().is_empty());
+        ///
+        /// assert_eq!(logger.try_get_default("http".parse::<u16>(), 80), 80);
+        /// assert_eq!(strip_ansi(logger.1.as_str()), "W&: ParseIntError { kind: InvalidDigit }\n");
+        /// ```
+    );
+
+    impl_try_get_retry!(
+        core::fmt::Debug,
+        ///
+        /// ```
+        /// use cand::{CaptureStorage, Logger, StatusLevel};
+        ///
+        /// let capture = CaptureStorage::new();
+        /// let mut logger = Logger((), capture.clone());
+        /// let mut failures = 2;
+        /// let reading = logger.try_get_retry(5, || {
+        ///     if failures > 0 {
+        ///         failures -= 1;
+        ///         return Err("sensor busy");
+        ///     }
+        ///     Ok(21.5)
+        /// });
+        /// assert_eq!(reading, Ok(21.5));
+        /// assert!(capture.contains(StatusLevel::Warning, "attempt 1/5 failed: \"sensor busy\""));
+        /// assert!(capture.contains(StatusLevel::Warning, "attempt 2/5 failed"));
+        /// assert_eq!(capture.len(), 2);
+        /// ```
+    );
+
+    impl_try_get!(
+        core::fmt::Debug,
+        owned,
+        ///
+        /// ```
+        /// use cand::{strip_ansi, Logger, StatusLevel, VecStorage};
+        ///
+        /// let logger = Logger((), VecStorage::new());
+        /// let (port, mut logger) = logger.try_get_or("http".parse::<u16>().map_err(Into::into), |logger| {
+        ///     logger.logdisp(StatusLevel::Info, "using the default port");
+        ///     8080
+        /// });
+        /// assert_eq!(port, 8080);
+        /// let out = strip_ansi(logger.1.as_str());
+        /// assert_eq!(out, "W&: invalid digit found in string\nI&: using the default port\n");
+        /// ```
+    );
+}
+
+impl<T: TimeProvider, S: StorageProvider> Logger<T, StatStorageProvider<S>> {
+    /// Counts of everything logged so far, see [`StatStorageProvider`].
+    pub fn stats(&self) -> LogStats {
+        self.1.stats()
+    }
+}
+
+impl<T: TimeProvider, S: TryStorageProvider> Logger<T, FallibleStorage<S>>
+where
+    S::Error: Debug,
+{
+    /// The most recent storage error, see [`FallibleStorage`].
+    pub fn last_error(&self) -> Option<&S::Error> {
+        self.1.last_error()
+    }
+}
+
+impl<T: TimeProvider + Clone, S: TryStorageProvider + Clone> MultiLogger<T, FallibleStorage<S>>
+where
+    S::Error: Debug + Clone,
+{
+    /// The most recent storage error, see [`FallibleStorage`].
+    pub fn last_error(&self) -> Option<&S::Error> {
+        self.1.last_error()
+    }
+}
+
+/// `try_run` and `try_run_get` for the borrowed loggers, in terms of their
+/// `log` and `logdisp`.
+macro_rules! impl_try_run_methods {
+    () => {
+        #[cfg(feature = "alloc")]
+        pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+            if let Err(err) = tryresult {
+                self.logdisp(StatusLevel::Error, ErrorChain(&*err));
+            }
+        }
+        #[cfg(feature = "alloc")]
+        pub fn try_run_get<O, F>(
+            &mut self,
+            tryresult: Result<O, Box<dyn core::error::Error>>,
+            value: F,
+        ) where
+            F: FnOnce(O),
+        {
+            match tryresult {
+                Ok(ok) => value(ok),
+                Err(err) => self.logdisp(StatusLevel::Error, ErrorChain(&*err)),
+            }
+        }
+
+        #[cfg(not(feature = "alloc"))]
+        pub fn try_run<O, E: core::fmt::Debug>(&mut self, tryresult: Result<O, E>) {
+            if let Err(err) = tryresult {
+                self.log(StatusLevel::Error, err);
+            }
+        }
+        #[cfg(not(feature = "alloc"))]
+        pub fn try_run_get<O, E: core::fmt::Debug, F>(&mut self, tryresult: Result<O, E>, value: F)
+        where
+            F: FnOnce(O),
+        {
+            match tryresult {
+                Ok(ok) => value(ok),
+                Err(err) => self.log(StatusLevel::Error, err),
+            }
+        }
+    };
+}
+
+/// `key=value ` for each pair, the prefix written by [`ContextLogger`].
+struct Context<'a>(&'a [(&'a str, &'a str)]);
+
+impl Display for Context<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (key, value) in self.0 {
+            write!(f, "{key}={value} ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned by [`Logger::with_context`], e.g.
+/// `logger.with_context(&[("req_id", "abc123")]).log_info("done")` logs
+/// `req_id=abc123 done`.
+///
+/// ```
+/// use cand::{strip_ansi, Logger, StatusLevel, VecStorage};
+///
+/// let mut logger = Logger((), VecStorage::new());
+/// let mut request = logger.with_context(&[("req_id", "abc123"), ("user", "42")]);
+/// request.log_kv(StatusLevel::Info, "served", &[("status", &200)]);
+/// request.try_run::<()>(Err("db timeout".into()));
+/// request.try_run_get(Ok::<_, Box<dyn std::error::Error>>(3), |_| {});
+/// request.flush();
+/// assert_eq!(
+///     strip_ansi(logger.1.as_str()),
+///     "I&: req_id=abc123 user=42 served status=200\nE&: req_id=abc123 user=42 db timeout\n"
+/// );
+/// ```
+pub struct ContextLogger<'a, T: TimeProvider, S: StorageProvider> {
+    logger: &'a mut Logger<T, S>,
+    context: &'a [(&'a str, &'a str)],
+}
+
+impl<T: TimeProvider, S: StorageProvider> ContextLogger<'_, T, S> {
+    pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
+        self.logger
+            .logdisp(level, format_args!("{}{:?}", Context(self.context), args));
+    }
+
+    pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
+        self.logger
+            .logdisp(level, format_args!("{}{}", Context(self.context), args));
+    }
+
+    /// Like [`Logger::log_kv`], with the context in front of `message`.
+    pub fn log_kv(&mut self, level: StatusLevel, message: impl Display, kv: &[(&str, &dyn Debug)]) {
+        self.logger.log_kv(
+            level,
+            format_args!("{}{}", Context(self.context), message),
+            kv,
+        );
+    }
+
+    /// Flushes the storage, see [`StorageProvider::flush`].
+    pub fn flush(&mut self) {
+        self.logger.flush();
+    }
+
+    impl_log_methods! {
+        log_err => StatusLevel::Error,
+        log_ok => StatusLevel::Ok,
+        log_warn => StatusLevel::Warning,
+        log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
+    }
+
+    impl_try_run_methods!();
+}
+
+/// Chain of scope names, displayed as `wifi/dhcp`.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    name: &'a str,
+}
+
+impl Display for Scope<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if let Some(parent) = self.parent {
+            write!(f, "{parent}/")?;
+        }
+        f.write_str(self.name)
+    }
+}
+
+macro_rules! impl_scoped_methods {
+    () => {
+        pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
+            self.logger
+                .logdisp(level, format_args!("[{}] {:?}", self.scope, args));
+        }
+
+        pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
+            self.logger
+                .logdisp(level, format_args!("[{}] {}", self.scope, args));
+        }
+
+        /// Like [`Logger::log_kv`], with the scope in front of `message`.
+        pub fn log_kv(
+            &mut self,
+            level: StatusLevel,
+            message: impl Display,
+            kv: &[(&str, &dyn Debug)],
+        ) {
+            self.logger
+                .log_kv(level, format_args!("[{}] {}", self.scope, message), kv);
+        }
+
+        /// Flushes the storage, see [`StorageProvider::flush`].
+        pub fn flush(&mut self) {
+            self.logger.flush();
+        }
+
+        impl_log_methods! {
+            log_err => StatusLevel::Error,
+            log_ok => StatusLevel::Ok,
+            log_warn => StatusLevel::Warning,
+            log_info => StatusLevel::Info,
+            log_critical => StatusLevel::Critical,
+        }
+
+        impl_try_run_methods!();
+    };
+}
+
+/// Returned by [`Logger::scoped`] and [`Logger::with_prefix`]; tags every
+/// message with `[scope] ` in the level color, and [`scoped`](Self::scoped)
+/// nests further scopes without allocating:
+///
+/// ```
+/// use cand::{set_color_mode, strip_ansi, ColorMode, ColorTheme, Logger, StatusLevel, VecStorage};
+///
+/// set_color_mode(ColorMode::Always);
+/// let mut logger = Logger((), VecStorage::new());
+/// let mut wifi = logger.scoped("wifi");
+/// wifi.logdisp(StatusLevel::Info, "connected");
+/// wifi.scoped("dhcp").logdisp(StatusLevel::Warning, "lease expired");
+///
+/// let out = logger.1.as_str();
+/// assert_eq!(strip_ansi(out), "I&: [wifi] connected\nW&: [wifi/dhcp] lease expired\n");
+/// let warning = ColorTheme::DEFAULT.color(StatusLevel::Warning);
+/// assert!(out.ends_with(&format!("{warning}[wifi/dhcp] lease expired\x1b[0m\n")));
+/// ```
+pub struct ScopedLogger<'a, T: TimeProvider, S: StorageProvider> {
+    logger: &'a mut Logger<T, S>,
+    scope: Scope<'a>,
+}
+
+impl<T: TimeProvider, S: StorageProvider> ScopedLogger<'_, T, S> {
+    /// Borrows this logger as a child scope, e.g. `wifi` → `wifi/dhcp`.
+    pub fn scoped<'b>(&'b mut self, name: &'b str) -> ScopedLogger<'b, T, S> {
+        ScopedLogger {
+            logger: &mut *self.logger,
+            scope: Scope {
+                parent: Some(&self.scope),
+                name,
+            },
+        }
+    }
+
+    impl_scoped_methods!();
+
+    impl_try_get_default!(core::fmt::Debug);
+
+    impl_try_get_retry!(core::fmt::Debug);
+
+    impl_try_get!(core::fmt::Debug, owned);
+}
+
+/// Returned by [`MultiLogger::scoped`]; owns a clone of the logger, so a
+/// `&'static str` scope can be moved into a thread.
+#[derive(Clone)]
+pub struct MultiScopedLogger<'a, T: TimeProvider + Clone, S: StorageProvider + Clone> {
+    logger: MultiLogger<T, S>,
+    scope: Scope<'a>,
+}
+
+impl<T: TimeProvider + Clone, S: StorageProvider + Clone> MultiScopedLogger<'_, T, S> {
+    /// Clones this logger into a child scope, e.g. `wifi` → `wifi/dhcp`.
+    pub fn scoped<'b>(&'b self, name: &'b str) -> MultiScopedLogger<'b, T, S> {
+        MultiScopedLogger {
+            logger: self.logger.clone(),
+            scope: Scope {
+                parent: Some(&self.scope),
+                name,
+            },
+        }
+    }
+
+    impl_scoped_methods!();
+
+    impl_try_get_default!(core::fmt::Debug);
+
+    impl_try_get_retry!(core::fmt::Debug);
+
+    impl_try_get!(core::fmt::Debug, cloned);
+}
+
+/// Returned by [`Logger::timed`]; logs `"label: <duration>"` when dropped, at
+/// `Warning` instead once [`warn_after`](Self::warn_after) is exceeded.
+///
+/// Durations come from the logger's [`TimeProvider`], so a [`TickClock`] works too:
+///
+/// ```
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use core::time::Duration;
+/// use cand::{strip_ansi, Logger, StatusLevel, TickClock, TickSource, TimeProvider, VecStorage};
+///
+/// static MILLIS: AtomicU64 = AtomicU64::new(0);
+/// struct Millis;
+/// impl TickSource for Millis {
+///     const TICKS_PER_SECOND: u64 = 1_000;
+///     fn ticks() -> u64 {
+///         MILLIS.load(Ordering::Relaxed)
+///     }
+/// }
+///
+/// let mut logger = Logger(TickClock::<Millis>::now(), VecStorage::new());
+/// let limit = Duration::from_millis(50);
+/// logger
+///     .timed(StatusLevel::Ok, "fast")
+///     .warn_after(limit)
+///     .run(|| MILLIS.fetch_add(20, Ordering::Relaxed));
+/// {
+///     let _slow = logger.timed(StatusLevel::Ok, "slow").warn_after(limit).log_start();
+///     MILLIS.fetch_add(80, Ordering::Relaxed);
+/// }
+///
+/// let out = strip_ansi(logger.1.as_str());
+/// let lines: Vec<_> = out.lines().map(|line| line.split_once(' ').unwrap().1).collect();
+/// assert_eq!(lines, ["fast: 20ms", "slow: started", "slow: 80ms"]);
+/// assert!(out.lines().nth(2).unwrap().starts_with("W&"));
+/// assert!(out.starts_with("O&"));
+/// ```
+#[must_use = "the duration is logged when the guard drops, bind it with `let _guard = ...`"]
+pub struct TimedGuard<'a, T: TimeProvider, S: StorageProvider> {
+    logger: &'a mut Logger<T, S>,
+    level: StatusLevel,
+    label: &'a str,
+    start: core::time::Duration,
+    warn_after: Option<core::time::Duration>,
+}
+
+impl<T: TimeProvider, S: StorageProvider> TimedGuard<'_, T, S> {
+    /// Logs at `Warning` instead when the block takes longer than `limit`.
+    pub fn warn_after(mut self, limit: core::time::Duration) -> Self {
+        self.warn_after = Some(limit);
+        self
+    }
+
+    /// Logs `"label: started"` at `Info` right away.
+    pub fn log_start(self) -> Self {
+        self.logger
+            .logdisp(StatusLevel::Info, format_args!("{}: started", self.label));
+        self
+    }
+
+    /// Runs `f`, drops the guard and returns what `f` returned.
+    pub fn run<R>(self, f: impl FnOnce() -> R) -> R {
+        f()
+    }
+}
+
+impl<T: TimeProvider, S: StorageProvider> core::ops::Deref for TimedGuard<'_, T, S> {
+    type Target = Logger<T, S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.logger
+    }
+}
+
+impl<T: TimeProvider, S: StorageProvider> core::ops::DerefMut for TimedGuard<'_, T, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.logger
+    }
+}
+
+impl<T: TimeProvider, S: StorageProvider> Drop for TimedGuard<'_, T, S> {
+    fn drop(&mut self) {
+        let took = self.logger.0.elapsed().saturating_sub(self.start);
+        let level = match self.warn_after {
+            Some(limit) if took > limit => StatusLevel::Warning,
+            _ => self.level,
+        };
+        self.logger.logdisp(
+            level,
+            format_args!("{}: {}", self.label, HumanDuration(took)),
+        );
+    }
+}
+
+/// Leading zeros that pad `value` to `width` (at most 3) digits.
+const fn zero_pad(value: u64, width: usize) -> &'static str {
+    let digits = if value >= 100 {
+        3
+    } else if value >= 10 {
+        2
+    } else {
+        1
+    };
+    ["", "0", "00"][width.saturating_sub(digits)]
+}
+
+/// Writes a duration with the largest fitting unit: `15ns`, `1.005s`, `3:07min`, `2d4h`.
+/// Shared by `Display` and ufmt through the given write macro.
+macro_rules! format_duration {
+    ($formatter:ident, $duration:expr, $write_macro:ident) => {{
+        let duration: core::time::Duration = $duration;
+        let secs = duration.as_secs();
+        let millis = duration.subsec_millis() as u64;
+
+        if duration.as_nanos() < 1_000 {
+            $write_macro!($formatter, "{}ns", duration.subsec_nanos())
+        } else if duration.as_micros() < 1_000 {
+            $write_macro!($formatter, "{}μs", duration.subsec_micros())
+        } else if duration.as_millis() < 1_000 {
+            $write_macro!($formatter, "{}ms", millis as u32)
+        } else if secs < 60 {
+            $write_macro!($formatter, "{}.{}{}s", secs, zero_pad(millis, 3), millis)
+        } else if secs < 3600 {
+            let (mins, secs) = (secs / 60, secs % 60);
+            $write_macro!($formatter, "{}:{}{}min", mins, zero_pad(secs, 2), secs)
+        } else if secs < 86400 {
+            let (hours, mins, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+            $write_macro!(
+                $formatter,
+                "{}:{}{}:{}{}",
+                hours,
+                zero_pad(mins, 2),
+                mins,
+                zero_pad(secs, 2),
+                secs
+            )
+        } else {
+            let (days, hours) = (secs / 86400, secs % 86400 / 3600);
+            if hours > 0 {
+                $write_macro!($formatter, "{}d{}h", days, hours)
+            } else {
+                $write_macro!($formatter, "{}d", days)
+            }
+        }
+    }};
+}
+
+/// Displays a duration for humans, e.g. `250ms`, `1.005s`, `3:20min`, `2d4h`.
+///
+/// ```
+/// use core::time::Duration;
+/// use cand::HumanDuration;
+///
+/// let cases = [
+///     (Duration::from_nanos(999), "999ns"),
+///     (Duration::from_nanos(1_000), "1μs"),
+///     (Duration::from_micros(999), "999μs"),
+///     (Duration::from_micros(1_000), "1ms"),
+///     (Duration::from_millis(999), "999ms"),
+///     (Duration::from_millis(1_000), "1.000s"),
+///     (Duration::from_millis(61_005), "1:01min"),
+///     (Duration::from_millis(59_999), "59.999s"),
+///     (Duration::from_millis(5_005), "5.005s"),
+///     (Duration::from_secs(60), "1:00min"),
+///     (Duration::from_secs(3_599), "59:59min"),
+///     (Duration::from_secs(3_600), "1:00:00"),
+///     (Duration::from_secs(86_399), "23:59:59"),
+///     (Duration::from_secs(86_400), "1d"),
+///     (Duration::from_secs(2 * 86_400 + 4 * 3_600 + 59), "2d4h"),
+/// ];
+/// for (duration, expected) in cases {
+///     assert_eq!(HumanDuration(duration).to_string(), expected, "{duration:?}");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HumanDuration(pub core::time::Duration);
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        format_duration!(f, self.0, write)
+    }
+}
+
+struct TimeFormatter<'a, T: TimeProvider>(&'a T);
+
+impl<'a, T: TimeProvider> core::fmt::Display for TimeFormatter<'a, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.write(f)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+pub struct UDebugStr<'a>(pub &'a str);
+
+#[cfg(feature = "ufmt")]
+impl<'a> Debug for UDebugStr<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<'a> uDebug for UDebugStr<'a> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str(self.0)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+struct UDebugDuration(Duration);
+
+#[cfg(feature = "ufmt")]
+impl uDebug for UDebugDuration {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        use ufmt::uwrite;
+        format_duration!(f, self.0, uwrite)
+    }
+}
+
+/// A whole [`ULogger`] line: level, time, colored payload, reset and newline.
+///
+/// Formatted in one pass, so a storage gets each message in a single
+/// `write_data` call and can write it atomically.
+#[cfg(feature = "ufmt")]
+struct URecord<D: uDebug> {
+    level: StatusLevel,
+    elapsed: Duration,
+    payload: D,
+}
+
+#[cfg(feature = "ufmt")]
+impl<D: uDebug> uDebug for URecord<D> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        uDebug::fmt(&self.level, f)?;
+        uDebug::fmt(&UDebugDuration(self.elapsed), f)?;
+        f.write_str(self.level.to_color())?;
+        self.payload.fmt(f)?;
+        f.write_str(reset())?;
+        f.write_str("\n")
+    }
+}
+
+/// `message` followed by its [`UKeyValues`], the payload of `log_kv`.
+#[cfg(feature = "ufmt")]
+struct UMessageKv<'a, K: UKeyValues>(&'a str, K);
+
+#[cfg(feature = "ufmt")]
+impl<K: UKeyValues> uDebug for UMessageKv<'_, K> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str(self.0)?;
+        self.1.fmt(f)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+macro_rules! impl_log_methods_ufmt {
+    ($($method:ident => $level:expr),* $(,)?) => {
+        $(
+            pub fn $method(&mut self, args: &str) {
+                if level_enabled!($level) {
+                    self.logdisp($level, args);
+                }
+            }
+        )*
+    };
+}
+
+/// Key-value pairs for `ULogger::log_kv`: a tuple of `(key, value)` pairs such as
+/// `(("bus", 0u8), ("addr", 0x48u8))`, written as ` key=value` without allocating.
+#[cfg(feature = "ufmt")]
+pub trait UKeyValues {
+    fn fmt<W: uWrite + ?Sized>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>;
+}
+
+#[cfg(feature = "ufmt")]
+impl UKeyValues for () {
+    fn fmt<W: uWrite + ?Sized>(&self, _f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+macro_rules! impl_ukey_values {
+    ($($value:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($value: uDebug),+> UKeyValues for ($((&str, $value),)+) {
+            fn fmt<W: uWrite + ?Sized>(
+                &self,
+                f: &mut ufmt::Formatter<'_, W>,
+            ) -> Result<(), W::Error> {
+                let ($($value,)+) = self;
+                $(
+                    f.write_str(" ")?;
+                    f.write_str($value.0)?;
+                    f.write_str("=")?;
+                    $value.1.fmt(f)?;
+                )+
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A);
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A, B);
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A, B, C);
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A, B, C, D);
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A, B, C, D, E);
+#[cfg(feature = "ufmt")]
+impl_ukey_values!(A, B, C, D, E, F);
+
+#[cfg(feature = "std")]
+#[cfg(feature = "ufmt")]
+struct StdWriter<'a>(&'a mut dyn std::io::Write);
+
+#[cfg(feature = "std")]
+#[cfg(feature = "ufmt")]
+impl<'a> uWrite for StdWriter<'a> {
+    type Error = ();
+    fn write_str(&mut self, s: &str) -> Result<(), ()> {
+        self.0.write_all(s.as_bytes()).map_err(|_| ())
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(feature = "ufmt")]
+impl UStorageProvider for () {
+    fn write_data(&mut self, d: impl uDebug, _level: &StatusLevel) {
+        use std::io::{self};
+        if PROGRESS_ON_STDOUT.swap(false, core::sync::atomic::Ordering::Relaxed) {
+            println!();
+        }
+        let mut stdout = io::stdout();
+        let mut writer = StdWriter(&mut stdout);
+        let _ = d.fmt(&mut ufmt::Formatter::new(&mut writer));
+    }
+}
+
+/// Logger built on `ufmt`, for targets where `core::fmt` is too large.
+///
+/// Each message reaches the storage as one `write_data` call, so a UART or DMA
+/// backend can send it in one go:
+///
+/// ```
+/// use cand::{StatusLevel, ULogger, UStorageProvider, VecStorage};
+///
+/// struct Counting(VecStorage, usize);
+///
+/// impl UStorageProvider for Counting {
+///     fn write_data(&mut self, d: impl ufmt::uDebug, level: &StatusLevel) {
+///         self.1 += 1;
+///         self.0.write_data(d, level);
+///     }
+/// }
+///
+/// let mut logger = ULogger((), Counting(VecStorage::new(), 0));
+/// logger.log_kv(StatusLevel::Warning, "i2c timeout", (("addr", 0x48u8),));
+/// assert_eq!(logger.1.1, 1);
+/// assert!(logger.1.0.as_str().contains("i2c timeout addr=72"));
+/// ```
+#[cfg(feature = "ufmt")]
+pub struct ULogger<T: TimeProvider, S: UStorageProvider>(pub T, pub S);
+
+#[cfg(feature = "ufmt")]
+#[derive(Clone)]
+pub struct MultiULogger<T: TimeProvider + Clone, S: UStorageProvider + Clone>(pub T, pub S);
+
+#[cfg(all(feature = "std", feature = "ufmt"))]
+impl ULogger<(), ()> {
+    /// Stdout without timestamps, same as `ULogger((), ())`.
+    pub fn stdout() -> Self {
+        ULogger((), ())
+    }
+}
+
+#[cfg(all(feature = "std", feature = "ufmt"))]
+impl ULogger<Instant, ()> {
+    /// Stdout with the time since creation, same as `ULogger(Instant::now(), ())`.
+    pub fn stdout_timed() -> Self {
+        ULogger(Instant::now(), ())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ULogger<(), NullStorage> {
+    /// Discards everything, see [`NullStorage`].
+    pub fn null() -> Self {
+        ULogger((), NullStorage)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl<T: TimeProvider, S: UStorageProvider> ULogger<T, S> {
+    /// Replaces the storage and returns the old one.
+    pub fn swap_storage(&mut self, storage: S) -> S {
+        core::mem::replace(&mut self.1, storage)
+    }
+
+    /// Takes the logger apart into its time provider and storage.
+    pub fn into_parts(self) -> (T, S) {
+        (self.0, self.1)
+    }
+
+    pub fn log(&mut self, level: StatusLevel, args: impl uDebug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() {
+            return;
+        }
+        self.1.write_record(&level, self.0.elapsed(), args);
+    }
+
+    pub fn logdisp(&mut self, level: StatusLevel, args: &str) {
+        self.log(level, UDebugStr(args));
+    }
+
+    /// Logs `message` followed by `key=value` pairs, see [`UKeyValues`].
+    ///
+    /// ```
+    /// use cand::{strip_ansi, StatusLevel, UDebugStr, ULogger, VecStorage};
+    ///
+    /// let mut logger = ULogger((), VecStorage::new());
+    /// logger.log_kv(StatusLevel::Warning, "i2c timeout", (("addr", 0x48u8), ("dev", UDebugStr("i2c1")), ("bus", -1i8)));
+    /// assert!(strip_ansi(logger.1.as_str()).ends_with("i2c timeout addr=72 dev=i2c1 bus=-1\n"));
+    /// ```
+    pub fn log_kv(&mut self, level: StatusLevel, message: &str, kv: impl UKeyValues) {
+        self.log(level, UMessageKv(message, kv));
+    }
+
+    /// Logs `args` followed by a dimmed `(file:line)`, e.g.
+    /// `ulogger.log_at(StatusLevel::Warning, cand::here!(), UDebugStr("low battery"))`.
+    pub fn log_at(&mut self, level: StatusLevel, location: &SourceLocation, args: impl uDebug) {
+        self.log(
+            level,
+            Located {
+                message: args,
+                location,
+            },
+        );
+    }
+
+    /// Logs a canonical hex dump of `data`, see [`Logger::log_hex`]. Nothing is
+    /// allocated; each byte goes through a 3-byte stack buffer.
+    pub fn log_hex(&mut self, level: StatusLevel, label: &str, data: &[u8]) {
+        self.log_hex_max(level, label, data, HEX_DUMP_MAX);
+    }
+
+    /// Like [`log_hex`](Self::log_hex), showing at most `max` bytes.
+    pub fn log_hex_max(&mut self, level: StatusLevel, label: &str, data: &[u8], max: usize) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() {
+            return;
+        }
+        let elapsed = self.0.elapsed();
+        // `I&:` and the time come before the message.
+        let mut prefix = CharCount((), 3);
+        let _ = uDebug::fmt(
+            &UDebugDuration(elapsed),
+            &mut ufmt::Formatter::new(&mut prefix),
+        );
+        let indent = core::cell::Cell::new(prefix.1);
+        let dump = HexDump {
+            label,
+            bytes: data,
+            indent: &indent,
+            max,
+        };
+        self.1.write_record(&level, elapsed, dump);
+    }
+
+    /// Logs `value` on the first and then every `rate`th call, counting in
+    /// `counter`, e.g. `sample_at_rate(&mut samples, 100, StatusLevel::Info, reading)`
+    /// in a 10 kHz loop. The caller owns the counter, so this needs no atomics.
+    pub fn sample_at_rate(
+        &mut self,
+        counter: &mut u32,
+        rate: u32,
+        level: StatusLevel,
+        value: impl uDebug,
+    ) {
+        let due = counter.is_multiple_of(rate.max(1));
+        *counter = counter.wrapping_add(1);
+        if due {
+            self.log(level, value);
+        }
+    }
+
+    impl_log_methods_ufmt! {
+        log_err => StatusLevel::Error,
+        log_ok => StatusLevel::Ok,
+        log_warn => StatusLevel::Warning,
+        log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg(not(feature = "ufmt"))]
+    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+        if let Err(err) = tryresult {
+            self.log(StatusLevel::Error, err);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg(not(feature = "ufmt"))]
+    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.log(StatusLevel::Error, err),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg(feature = "ufmt")]
+    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+        if let Err(err) = tryresult {
+            self.log(
+                StatusLevel::Error,
+                UDebugStr(&ErrorChain(&*err).to_string()),
+            );
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cfg(feature = "ufmt")]
+    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.log(
+                StatusLevel::Error,
+                UDebugStr(&ErrorChain(&*err).to_string()),
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run<O, E: ufmt::uDebug>(&mut self, tryresult: Result<O, E>) {
+        if let Err(err) = tryresult {
+            self.log(StatusLevel::Error, err);
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    pub fn try_run_get<O, E: ufmt::uDebug, F>(&mut self, tryresult: Result<O, E>, value: F)
+    where
+        F: FnOnce(O),
+    {
+        match tryresult {
+            Ok(ok) => value(ok),
+            Err(err) => self.log(StatusLevel::Error, err),
+        }
+    }
 
-#[derive(Clone)]
-pub struct MultiLogger<T: TimeProvider + Clone, S: StorageProvider + Clone>(pub T, pub S);
+    impl_try_get_default!(ufmt::uDebug);
 
-pub struct Logger<T: TimeProvider, S: StorageProvider>(pub T, pub S);
+    impl_try_get_retry!(ufmt::uDebug);
+
+    impl_try_get!(ufmt::uDebug, owned);
+}
 
-impl<'a, T: TimeProvider + Clone, S: StorageProvider + Clone> MultiLogger<T, S>
+#[cfg(feature = "ufmt")]
+impl<T: TimeProvider + Clone, S: UStorageProvider + Clone> MultiULogger<T, S>
 where
     Self: Clone,
 {
-    pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
-        self.1.write_data(
-            format_args!(
-                "{:?}{} {}{:?}{}\n",
-                level,
-                TimeFormatter(&self.0),
-                level.to_color(),
-                args,
-                RESET
-            ),
-            &level,
+    pub fn log(&mut self, level: StatusLevel, args: impl uDebug) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() {
+            return;
+        }
+        self.1.write_record(&level, self.0.elapsed(), args);
+    }
+
+    pub fn logdisp(&mut self, level: StatusLevel, args: &str) {
+        self.log(level, UDebugStr(args));
+    }
+
+    /// Logs `message` followed by `key=value` pairs, see [`UKeyValues`].
+    pub fn log_kv(&mut self, level: StatusLevel, message: &str, kv: impl UKeyValues) {
+        self.log(level, UMessageKv(message, kv));
+    }
+
+    /// Logs `args` followed by a dimmed `(file:line)`, e.g.
+    /// `ulogger.log_at(StatusLevel::Warning, cand::here!(), UDebugStr("low battery"))`.
+    pub fn log_at(&mut self, level: StatusLevel, location: &SourceLocation, args: impl uDebug) {
+        self.log(
+            level,
+            Located {
+                message: args,
+                location,
+            },
         );
     }
 
-    pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
-        self.1.write_data(
-            format_args!(
-                "{:?}{} {}{}{}\n",
-                level,
-                TimeFormatter(&self.0),
-                level.to_color(),
-                args,
-                RESET
-            ),
-            &level,
+    /// Logs a canonical hex dump of `data`, see [`Logger::log_hex`].
+    pub fn log_hex(&mut self, level: StatusLevel, label: &str, data: &[u8]) {
+        self.log_hex_max(level, label, data, HEX_DUMP_MAX);
+    }
+
+    /// Like [`log_hex`](Self::log_hex), showing at most `max` bytes.
+    pub fn log_hex_max(&mut self, level: StatusLevel, label: &str, data: &[u8], max: usize) {
+        if level.severity() < STATIC_MAX_LEVEL.severity() {
+            return;
+        }
+        let elapsed = self.0.elapsed();
+        // `I&:` and the time come before the message.
+        let mut prefix = CharCount((), 3);
+        let _ = uDebug::fmt(
+            &UDebugDuration(elapsed),
+            &mut ufmt::Formatter::new(&mut prefix),
         );
+        let indent = core::cell::Cell::new(prefix.1);
+        let dump = HexDump {
+            label,
+            bytes: data,
+            indent: &indent,
+            max,
+        };
+        self.1.write_record(&level, elapsed, dump);
     }
 
-    impl_log_methods! {
+    /// Logs `value` on the first and then every `rate`th call, counting in
+    /// `counter`, e.g. `sample_at_rate(&mut samples, 100, StatusLevel::Info, reading)`
+    /// in a 10 kHz loop. The caller owns the counter, so this needs no atomics.
+    pub fn sample_at_rate(
+        &mut self,
+        counter: &mut u32,
+        rate: u32,
+        level: StatusLevel,
+        value: impl uDebug,
+    ) {
+        let due = counter.is_multiple_of(rate.max(1));
+        *counter = counter.wrapping_add(1);
+        if due {
+            self.log(level, value);
+        }
+    }
+
+    impl_log_methods_ufmt! {
         log_err => StatusLevel::Error,
         log_ok => StatusLevel::Ok,
         log_warn => StatusLevel::Warning,
         log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
     }
 
     #[cfg(feature = "alloc")]
     pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
         if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, err);
+            self.log(
+                StatusLevel::Error,
+                UDebugStr(&ErrorChain(&*err).to_string()),
+            );
         }
     }
+
     #[cfg(feature = "alloc")]
     pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
     where
@@ -327,18 +7008,21 @@ where
     {
         match tryresult {
             Ok(ok) => value(ok),
-            Err(err) => self.log(StatusLevel::Error, err),
+            Err(err) => self.log(
+                StatusLevel::Error,
+                UDebugStr(&ErrorChain(&*err).to_string()),
+            ),
         }
     }
 
     #[cfg(not(feature = "alloc"))]
-    pub fn try_run<O, E: core::fmt::Debug>(&mut self, tryresult: Result<O, E>) {
+    pub fn try_run<O, E: ufmt::uDebug>(&mut self, tryresult: Result<O, E>) {
         if let Err(err) = tryresult {
             self.log(StatusLevel::Error, err);
         }
     }
     #[cfg(not(feature = "alloc"))]
-    pub fn try_run_get<O, E: core::fmt::Debug, F>(&mut self, tryresult: Result<O, E>, value: F)
+    pub fn try_run_get<O, E: ufmt::uDebug, F>(&mut self, tryresult: Result<O, E>, value: F)
     where
         F: FnOnce(O),
     {
@@ -348,70 +7032,289 @@ where
         }
     }
 
-    impl_try_get!(core::fmt::Debug, cloned);
+    impl_try_get_default!(ufmt::uDebug);
+
+    impl_try_get_retry!(ufmt::uDebug);
+
+    impl_try_get!(ufmt::uDebug, cloned);
 }
 
-impl<'a, T: TimeProvider, S: StorageProvider> Logger<T, S> {
-    pub fn log(&mut self, level: StatusLevel, args: impl Debug) {
-        self.1.write_data(
-            format_args!(
-                "{:?}{} {}{:?}{}\n",
-                level,
-                TimeFormatter(&self.0),
-                level.to_color(),
-                args,
-                RESET
-            ),
-            &level,
-        );
+/// `log::Debug` and `log::Trace` have no counterpart and map to the lowest severity, `Ok`.
+#[cfg(feature = "log-compat")]
+impl From<log::Level> for StatusLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => StatusLevel::Error,
+            log::Level::Warn => StatusLevel::Warning,
+            log::Level::Info => StatusLevel::Info,
+            log::Level::Debug | log::Level::Trace => StatusLevel::Ok,
+        }
     }
+}
 
-    pub fn logdisp(&mut self, level: StatusLevel, args: impl Display) {
-        self.1.write_data(
-            format_args!(
-                "{:?}{} {}{}{}\n",
-                level,
-                TimeFormatter(&self.0),
-                level.to_color(),
-                args,
-                RESET
-            ),
-            &level,
+/// Backend for the `log` crate, so `log::info!` and friends go through a [`Logger`].
+///
+/// Messages are written as `target: message`; filter them with the logger's
+/// storage, e.g. `Logger(...).with_min_level(StatusLevel::Warning)`.
+///
+/// ```
+/// use cand::{CandLog, CaptureStorage, Logger, StatusLevel};
+///
+/// let capture = CaptureStorage::new();
+/// CandLog::new(Logger((), capture.clone())).init(log::LevelFilter::Info)?;
+/// log::warn!(target: "net", "timeout after {}ms", 250);
+/// log::debug!(target: "net", "retrying");
+///
+/// assert!(capture.contains(StatusLevel::Warning, "net: timeout after 250ms"));
+/// assert_eq!(capture.len(), 1);
+/// # Ok::<(), log::SetLoggerError>(())
+/// ```
+#[cfg(feature = "log-compat")]
+pub struct LogFacadeStorageProvider<T: TimeProvider, S: StorageProvider>(
+    pub std::sync::Mutex<Logger<T, S>>,
+);
+
+/// Shorter name for [`LogFacadeStorageProvider`].
+#[cfg(feature = "log-compat")]
+pub use LogFacadeStorageProvider as CandLog;
+
+#[cfg(feature = "log-compat")]
+impl<T: TimeProvider, S: StorageProvider> LogFacadeStorageProvider<T, S> {
+    pub fn new(logger: Logger<T, S>) -> Self {
+        Self(std::sync::Mutex::new(logger))
+    }
+
+    fn logger(&self) -> std::sync::MutexGuard<'_, Logger<T, S>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "log-compat")]
+impl<T, S> LogFacadeStorageProvider<T, S>
+where
+    T: TimeProvider + Send + 'static,
+    S: StorageProvider + Send + 'static,
+{
+    /// Installs this as the global `log` backend. Can only succeed once per process.
+    pub fn init(self, min_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_logger(Box::leak(Box::new(self)))?;
+        log::set_max_level(min_level);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "log-compat")]
+impl<T, S> log::Log for LogFacadeStorageProvider<T, S>
+where
+    T: TimeProvider + Send,
+    S: StorageProvider + Send,
+{
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.logger().1.enabled(&metadata.level().into())
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.logger().logdisp(
+            record.level().into(),
+            format_args!("{}: {}", record.target(), record.args()),
         );
     }
 
+    fn flush(&self) {}
+}
+
+/// Routes the `log` crate to the default stdout logger.
+#[cfg(feature = "log-compat")]
+pub fn init_log_facade(min_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+    LogFacadeStorageProvider::new(Logger(Instant::now(), ())).init(min_level)
+}
+
+/// A [`Logger`] behind a lock, so it can be shared and used through `&self`.
+///
+/// On std it holds an `Arc<Mutex<_>>` and clones cheaply; with the
+/// `critical-section` feature and no std it holds a `critical_section::Mutex`
+/// and can be a `static`. Each message is written under a single lock, so
+/// lines from different threads never interleave.
+///
+/// ```
+/// use cand::{CaptureStorage, Logger, StatusLevel, SyncLogger};
+///
+/// let capture = CaptureStorage::new();
+/// let logger = SyncLogger::new(Logger((), capture.clone()));
+/// let threads: Vec<_> = (0..8)
+///     .map(|thread| {
+///         let logger = logger.clone();
+///         std::thread::spawn(move || {
+///             for n in 0..100 {
+///                 logger.logdisp(StatusLevel::Info, format_args!("thread {thread} message {n}"));
+///             }
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+///
+/// let messages = capture.messages();
+/// assert_eq!(messages.len(), 800);
+/// for thread in 0..8 {
+///     let lines = messages.iter().filter(|(_, text)| text.starts_with(&format!("thread {thread} ")));
+///     assert!(lines.map(|(_, text)| text.as_str()).eq((0..100).map(|n| format!("thread {thread} message {n}"))));
+/// }
+/// ```
+///
+/// The raw output is whole lines too:
+///
+/// ```
+/// use cand::{strip_ansi, Logger, StatusLevel, SyncLogger, VecStorage};
+///
+/// let logger = SyncLogger::new(Logger((), VecStorage::new()));
+/// let threads: Vec<_> = (0..8)
+///     .map(|thread| {
+///         let logger = logger.clone();
+///         std::thread::spawn(move || {
+///             for n in 0..100 {
+///                 logger.logdisp(StatusLevel::Info, format_args!("thread {thread} message {n}"));
+///             }
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+///
+/// let out = logger.with(|logger| strip_ansi(logger.1.as_str())).unwrap();
+/// assert_eq!(out.lines().count(), 800);
+/// for line in out.lines() {
+///     let (thread, n) = line.strip_prefix("I&: thread ").unwrap().split_once(" message ").unwrap();
+///     assert!(thread.parse::<u8>().unwrap() < 8 && n.parse::<u8>().unwrap() < 100, "{line}");
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct SyncLogger<T: TimeProvider, S: StorageProvider>(
+    std::sync::Arc<std::sync::Mutex<Logger<T, S>>>,
+);
+
+#[cfg(feature = "std")]
+impl<T: TimeProvider, S: StorageProvider> Clone for SyncLogger<T, S> {
+    fn clone(&self) -> Self {
+        SyncLogger(self.0.clone())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: TimeProvider, S: StorageProvider> SyncLogger<T, S> {
+    pub fn new(logger: Logger<T, S>) -> Self {
+        SyncLogger(std::sync::Arc::new(std::sync::Mutex::new(logger)))
+    }
+
+    /// Runs `f` with the logger locked; always `Some` on std.
+    ///
+    /// Logging through the same `SyncLogger` from inside `f` deadlocks.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Logger<T, S>) -> R) -> Option<R> {
+        Some(f(&mut self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)))
+    }
+}
+
+/// A [`Logger`] behind a lock, so it can be shared and used through `&self`,
+/// e.g. from a `static` and interrupt handlers.
+///
+/// Messages logged while the logger is already in use, e.g. from inside
+/// [`with`](Self::with), are dropped and counted in [`dropped`](Self::dropped).
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+pub struct SyncLogger<T: TimeProvider, S: StorageProvider>(
+    critical_section::Mutex<core::cell::RefCell<Logger<T, S>>>,
+    critical_section::Mutex<core::cell::Cell<u32>>,
+);
+
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+impl<T: TimeProvider, S: StorageProvider> SyncLogger<T, S> {
+    pub const fn new(logger: Logger<T, S>) -> Self {
+        SyncLogger(
+            critical_section::Mutex::new(core::cell::RefCell::new(logger)),
+            critical_section::Mutex::new(core::cell::Cell::new(0)),
+        )
+    }
+
+    /// Runs `f` with the logger locked, or returns `None` and counts a drop when
+    /// it is already in use, e.g. when logging through it from inside `f`.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Logger<T, S>) -> R) -> Option<R> {
+        critical_section::with(|cs| match self.0.borrow(cs).try_borrow_mut() {
+            Ok(mut logger) => Some(f(&mut logger)),
+            Err(_) => {
+                let dropped = self.1.borrow(cs);
+                dropped.set(dropped.get().saturating_add(1));
+                None
+            }
+        })
+    }
+
+    /// Calls turned away because the logger was already in use, since creation.
+    pub fn dropped(&self) -> u32 {
+        critical_section::with(|cs| self.1.borrow(cs).get())
+    }
+}
+
+#[cfg(any(feature = "std", feature = "critical-section"))]
+impl<T: TimeProvider, S: StorageProvider> SyncLogger<T, S> {
+    pub fn log(&self, level: StatusLevel, args: impl Debug) {
+        self.with(|logger| logger.log(level, args));
+    }
+
+    pub fn logdisp(&self, level: StatusLevel, args: impl Display) {
+        self.with(|logger| logger.logdisp(level, args));
+    }
+
+    /// Same as [`Logger::log_kv`].
+    pub fn log_kv(&self, level: StatusLevel, message: impl Display, kv: &[(&str, &dyn Debug)]) {
+        self.with(|logger| logger.log_kv(level, message, kv));
+    }
+
+    pub fn flush(&self) {
+        self.with(|logger| logger.flush());
+    }
+
     impl_log_methods! {
+        shared:
         log_err => StatusLevel::Error,
         log_ok => StatusLevel::Ok,
         log_warn => StatusLevel::Warning,
         log_info => StatusLevel::Info,
+        log_critical => StatusLevel::Critical,
     }
 
     #[cfg(feature = "alloc")]
-    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
+    pub fn try_run<O>(&self, tryresult: Result<O, Box<dyn core::error::Error>>) {
         if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, err);
+            self.logdisp(StatusLevel::Error, ErrorChain(&*err));
         }
     }
+    /// Same as [`Logger::try_run_get`]; `value` runs outside the lock, so it may log.
     #[cfg(feature = "alloc")]
-    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
+    pub fn try_run_get<O, F>(&self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
     where
         F: FnOnce(O),
     {
         match tryresult {
             Ok(ok) => value(ok),
-            Err(err) => self.log(StatusLevel::Error, err),
+            Err(err) => self.logdisp(StatusLevel::Error, ErrorChain(&*err)),
         }
     }
 
     #[cfg(not(feature = "alloc"))]
-    pub fn try_run<O, E: core::fmt::Debug>(&mut self, tryresult: Result<O, E>) {
+    pub fn try_run<O, E: core::fmt::Debug>(&self, tryresult: Result<O, E>) {
         if let Err(err) = tryresult {
             self.log(StatusLevel::Error, err);
         }
     }
+    /// Same as [`Logger::try_run_get`]; `value` runs outside the lock, so it may log.
     #[cfg(not(feature = "alloc"))]
-    pub fn try_run_get<O, E: core::fmt::Debug, F>(&mut self, tryresult: Result<O, E>, value: F)
+    pub fn try_run_get<O, E: core::fmt::Debug, F>(&self, tryresult: Result<O, E>, value: F)
     where
         F: FnOnce(O),
     {
@@ -420,383 +7323,1076 @@ impl<'a, T: TimeProvider, S: StorageProvider> Logger<T, S> {
             Err(err) => self.log(StatusLevel::Error, err),
         }
     }
+}
 
-    impl_try_get!(core::fmt::Debug, owned);
+/// Object-safe logging interface, used to store any logger as the global one.
+pub trait GlobalLogger {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments);
 }
 
-struct TimeFormatter<'a, T: TimeProvider>(&'a T);
+impl<T: TimeProvider, S: StorageProvider> GlobalLogger for Logger<T, S> {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments) {
+        self.logdisp(level, args);
+    }
+}
 
-impl<'a, T: TimeProvider> core::fmt::Display for TimeFormatter<'a, T> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        self.0.write(f)
+impl<T: TimeProvider + Clone, S: StorageProvider + Clone> GlobalLogger for MultiLogger<T, S> {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments) {
+        self.logdisp(level, args);
+    }
+}
+
+#[cfg(any(feature = "std", feature = "critical-section"))]
+impl<T: TimeProvider, S: StorageProvider> GlobalLogger for SyncLogger<T, S> {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments) {
+        self.logdisp(level, args);
     }
 }
 
 #[cfg(feature = "ufmt")]
-pub struct UDebugStr<'a>(pub &'a str);
+impl<T: TimeProvider, S: UStorageProvider> GlobalLogger for ULogger<T, S> {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments) {
+        self.log(level, UArguments(args));
+    }
+}
 
 #[cfg(feature = "ufmt")]
-impl<'a> Debug for UDebugStr<'a> {
+impl<T: TimeProvider + Clone, S: UStorageProvider + Clone> GlobalLogger for MultiULogger<T, S> {
+    fn log_args(&mut self, level: StatusLevel, args: Arguments) {
+        self.log(level, UArguments(args));
+    }
+}
+
+/// Logs the error of a `Result` in passing, e.g.
+/// `std::fs::read(path).log_err(&mut logger, "reading config")?`.
+///
+/// Every method returns the `Result` unchanged. Works with any [`GlobalLogger`].
+///
+/// ```
+/// use cand::{CandResultExt, CaptureStorage, Logger, StatusLevel};
+///
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// let port = "http".parse::<u16>().log_err(&mut logger, "parsing port").unwrap_or(80);
+/// assert_eq!(port, 80);
+/// assert!(capture.contains(StatusLevel::Error, "parsing port: ParseIntError"));
+///
+/// let path = "/etc/app.toml";
+/// let port = "8080"
+///     .parse::<u16>()
+///     .context_err(&mut logger, || format!("port in {path}"));
+/// assert_eq!(port, Ok(8080));
+/// assert_eq!(capture.len(), 1);
+/// ```
+pub trait CandResultExt: Sized {
+    /// Logs `context: <error>` at `Error` if this is an `Err`.
+    fn log_err<L: GlobalLogger + ?Sized>(self, logger: &mut L, context: &str) -> Self;
+
+    /// Logs `context: <error>` at `Warning` if this is an `Err`.
+    fn log_warn<L: GlobalLogger + ?Sized>(self, logger: &mut L, context: &str) -> Self;
+
+    /// Like [`log_err`](Self::log_err), but `context` only runs on `Err`.
+    fn context_err<L: GlobalLogger + ?Sized, D: Display>(
+        self,
+        logger: &mut L,
+        context: impl FnOnce() -> D,
+    ) -> Self;
+}
+
+impl<T, E: Debug> CandResultExt for Result<T, E> {
+    fn log_err<L: GlobalLogger + ?Sized>(self, logger: &mut L, context: &str) -> Self {
+        self.context_err(logger, || context)
+    }
+
+    fn log_warn<L: GlobalLogger + ?Sized>(self, logger: &mut L, context: &str) -> Self {
+        if let Err(err) = &self {
+            logger.log_args(StatusLevel::Warning, format_args!("{context}: {err:?}"));
+        }
+        self
+    }
+
+    fn context_err<L: GlobalLogger + ?Sized, D: Display>(
+        self,
+        logger: &mut L,
+        context: impl FnOnce() -> D,
+    ) -> Self {
+        if let Err(err) = &self {
+            logger.log_args(StatusLevel::Error, format_args!("{}: {:?}", context(), err));
+        }
+        self
+    }
+}
+
+/// Logs a missing value in passing, e.g. `cache.get(key).log_none(&mut logger, "cache miss")`.
+///
+/// ```
+/// use cand::{CandOptionExt, CaptureStorage, Logger, StatusLevel};
+///
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// let cached: Option<u32> = None;
+/// assert_eq!(cached.log_none(&mut logger, "cache miss"), None);
+/// assert!(capture.contains(StatusLevel::Warning, "cache miss"));
+/// ```
+pub trait CandOptionExt: Sized {
+    /// Logs `message` at `Warning` if this is `None`, and returns `self` unchanged.
+    fn log_none<L: GlobalLogger + ?Sized>(self, logger: &mut L, message: &str) -> Self;
+}
+
+impl<T> CandOptionExt for Option<T> {
+    fn log_none<L: GlobalLogger + ?Sized>(self, logger: &mut L, message: &str) -> Self {
+        if self.is_none() {
+            logger.log_args(StatusLevel::Warning, format_args!("{message}"));
+        }
+        self
+    }
+}
+
+/// Writes `core::fmt` arguments through a ufmt formatter, without buffering.
+#[cfg(feature = "ufmt")]
+struct UArguments<'a>(Arguments<'a>);
+
+#[cfg(feature = "ufmt")]
+impl uDebug for UArguments<'_> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        struct Adapter<'f, 'w, W: uWrite + ?Sized> {
+            formatter: &'f mut ufmt::Formatter<'w, W>,
+            error: Option<W::Error>,
+        }
+
+        impl<W: uWrite + ?Sized> core::fmt::Write for Adapter<'_, '_, W> {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.formatter.write_str(s).map_err(|error| {
+                    self.error = Some(error);
+                    core::fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            formatter: f,
+            error: None,
+        };
+        match core::fmt::write(&mut adapter, self.0) {
+            Ok(()) => Ok(()),
+            Err(_) => adapter.error.map_or(Ok(()), Err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+static GLOBAL: std::sync::Mutex<Option<Box<dyn GlobalLogger + Send>>> = std::sync::Mutex::new(None);
+
+/// Installs (or replaces) the logger used by [`info!`], [`warn!`] and the other free macros.
+///
+/// ```
+/// use cand::{level_enabled, CaptureStorage, Logger, StatusLevel};
+///
+/// // Before a logger is installed the macros do nothing.
+/// cand::crit!("lost");
+/// assert!(cand::with_global(|_| ()).is_none());
+///
+/// let capture = CaptureStorage::new();
+/// cand::set_global(Logger((), capture.clone()));
+/// cand::info!("listening on {}:{}", "0.0.0.0", 8080);
+/// cand::warn!("{} retries left", 2);
+/// cand::error!("disk {} failing", "sda");
+/// cand::crit!("brownout at {}mV", 2900);
+///
+/// let info = capture.contains(StatusLevel::Info, "listening on 0.0.0.0:8080");
+/// assert_eq!(info, level_enabled!(StatusLevel::Info));
+/// let warning = capture.contains(StatusLevel::Warning, "2 retries left");
+/// assert_eq!(warning, level_enabled!(StatusLevel::Warning));
+/// let error = capture.contains(StatusLevel::Error, "disk sda failing");
+/// assert_eq!(error, level_enabled!(StatusLevel::Error));
+/// assert!(capture.contains(StatusLevel::Critical, "brownout at 2900mV"));
+/// assert!(!capture.contains(StatusLevel::Critical, "lost"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_global(logger: impl GlobalLogger + Send + 'static) {
+    *GLOBAL
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Box::new(logger));
+}
+
+/// Runs `f` with the global logger, or returns `None` if none is set.
+///
+/// Logging through the global logger from inside `f` deadlocks.
+#[cfg(feature = "std")]
+pub fn with_global<R>(f: impl FnOnce(&mut dyn GlobalLogger) -> R) -> Option<R> {
+    let mut global = GLOBAL
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    global
+        .as_deref_mut()
+        .map(|logger| f(logger as &mut dyn GlobalLogger))
+}
+
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+static GLOBAL: critical_section::Mutex<
+    core::cell::RefCell<Option<&'static mut (dyn GlobalLogger + Send)>>,
+> = critical_section::Mutex::new(core::cell::RefCell::new(None));
+
+/// Installs (or replaces) the logger used by [`info!`], [`warn!`] and the other free macros.
+///
+/// Without std the logger must live forever, e.g. in a `static_cell::StaticCell`.
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+pub fn set_global(logger: &'static mut (dyn GlobalLogger + Send)) {
+    critical_section::with(|cs| *GLOBAL.borrow_ref_mut(cs) = Some(logger));
+}
+
+/// Runs `f` with the global logger, or returns `None` if none is set.
+///
+/// Also returns `None` when the global logger is already in use, e.g. when
+/// logging through it from inside `f` or from a panic raised while it logs.
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+pub fn with_global<R>(f: impl FnOnce(&mut dyn GlobalLogger) -> R) -> Option<R> {
+    critical_section::with(|cs| {
+        let mut global = GLOBAL.borrow(cs).try_borrow_mut().ok()?;
+        global
+            .as_deref_mut()
+            .map(|logger| f(logger as &mut dyn GlobalLogger))
+    })
+}
+
+/// Formats a `core` panic like the std hook does, for `#[panic_handler]` functions.
+#[cfg(any(
+    feature = "ufmt",
+    all(not(feature = "std"), feature = "critical-section")
+))]
+struct CorePanicMessage<'a, M: Display> {
+    location: Option<&'a core::panic::Location<'a>>,
+    message: M,
+}
+
+#[cfg(any(
+    feature = "ufmt",
+    all(not(feature = "std"), feature = "critical-section")
+))]
+impl<'a> CorePanicMessage<'a, core::panic::PanicMessage<'a>> {
+    fn of(info: &'a core::panic::PanicInfo<'a>) -> Self {
+        Self {
+            location: info.location(),
+            message: info.message(),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "ufmt",
+    all(not(feature = "std"), feature = "critical-section")
+))]
+impl<M: Display> Display for CorePanicMessage<'_, M> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.0)?;
-        Ok(())
+        write!(f, "{}panicked at ", reset())?;
+        match self.location {
+            Some(location) => write!(
+                f,
+                "{}:{}:{}:",
+                location.file(),
+                location.line(),
+                location.column()
+            )?,
+            None => f.write_str("unknown location:")?,
+        }
+        let color = StatusLevel::Critical.to_color();
+        write!(f, "\n{}{}{}", color, self.message, reset())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+#[doc(hidden)]
+pub fn __log_panic(logger: &mut impl GlobalLogger, info: &core::panic::PanicInfo) {
+    logger.log_args(
+        StatusLevel::Critical,
+        format_args!("{}", CorePanicMessage::of(info)),
+    );
+}
+
+/// What [`__log_panic`] writes, from the parts of a `PanicInfo`; a `PanicInfo`
+/// itself only exists inside a `#[panic_handler]`, so this is what host tests call.
+///
+/// ```
+/// use cand::{strip_ansi, ULogger, VecStorage};
+///
+/// let mut logger = ULogger((), VecStorage::new());
+/// let location = core::panic::Location::caller();
+/// cand::__log_panic_parts(&mut logger, Some(location), format_args!("index {} out of range", 9));
+/// let expected = format!("C&:0nspanicked at {}:{}:{}:\nindex 9 out of range\n", file!(), location.line(), location.column());
+/// assert_eq!(strip_ansi(logger.1.as_str()), expected);
+/// ```
+#[cfg(feature = "ufmt")]
+#[doc(hidden)]
+pub fn __log_panic_parts(
+    logger: &mut impl GlobalLogger,
+    location: Option<&core::panic::Location>,
+    message: Arguments,
+) {
+    logger.log_args(
+        StatusLevel::Critical,
+        format_args!("{}", CorePanicMessage { location, message }),
+    );
+}
+
+/// Defines a `#[panic_handler]` that logs the panic at `Critical` level through a
+/// [`ULogger`] (or any [`GlobalLogger`]), e.g. the UART or RTT one used for normal
+/// logging. Nothing is allocated.
+///
+/// The logger expression is evaluated inside the handler. Afterwards the handler
+/// spins forever, or calls the reset function given as second argument.
+///
+/// ```ignore
+/// cand::black_box_cand_ufmt!(ULogger(ticks(), UartStorage::steal()));
+/// // or
+/// cand::black_box_cand_ufmt!(ULogger(ticks(), UartStorage::steal()), reset_mcu);
+/// ```
+#[cfg(feature = "ufmt")]
+#[macro_export]
+macro_rules! black_box_cand_ufmt {
+    ($logger_expr:expr) => {
+        #[panic_handler]
+        fn __cand_panic(info: &::core::panic::PanicInfo) -> ! {
+            $crate::__log_panic(&mut $logger_expr, info);
+            loop {
+                ::core::hint::spin_loop();
+            }
+        }
+    };
+
+    ($logger_expr:expr, $reset:expr) => {
+        #[panic_handler]
+        fn __cand_panic(info: &::core::panic::PanicInfo) -> ! {
+            $crate::__log_panic(&mut $logger_expr, info);
+            ($reset)()
+        }
+    };
+}
+
+/// Helpers for a `#[panic_handler]` that logs through cand, without allocating.
+///
+/// ```ignore
+/// #[panic_handler]
+/// fn panic(info: &core::panic::PanicInfo) -> ! {
+///     cand::panic::handler_with_reset(info, reset_mcu)
+/// }
+///
+/// // at startup, with a logger that lives forever
+/// cand::panic::install(LOGGER.init(ULogger(ticks, UartStorage { serial })));
+/// ```
+#[cfg(all(not(feature = "std"), feature = "critical-section"))]
+pub mod panic {
+    use crate::{GlobalLogger, StatusLevel};
+
+    /// Stores the logger panics are written to; the same one as [`crate::set_global`].
+    pub fn install(logger: &'static mut (dyn GlobalLogger + Send)) {
+        crate::set_global(logger);
+    }
+
+    /// Logs the panic at `Critical` level and then spins forever.
+    pub fn handler(info: &core::panic::PanicInfo) -> ! {
+        log(info);
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Logs the panic at `Critical` level and then calls `reset`, e.g. a watchdog reset.
+    pub fn handler_with_reset(info: &core::panic::PanicInfo, reset: fn() -> !) -> ! {
+        log(info);
+        reset()
+    }
+
+    fn log(info: &core::panic::PanicInfo) {
+        crate::log_global(
+            StatusLevel::Critical,
+            format_args!("{}", crate::CorePanicMessage::of(info)),
+        );
     }
 }
 
-#[cfg(feature = "ufmt")]
-impl<'a> uDebug for UDebugStr<'a> {
-    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
-    where
-        W: uWrite + ?Sized,
-    {
-        f.write_str(self.0)?;
-        Ok(())
-    }
+/// Logs through the global logger; does nothing if none is set.
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub fn log_global(level: StatusLevel, args: Arguments) {
+    with_global(|logger| logger.log_args(level, args));
+}
+
+/// Logs an `Ok` message through the global logger, e.g. `ok!("ready in {}ms", ms)`,
+/// or through a [`Logger`] or [`MultiLogger`] given first: `ok!(logger, "ready in {}ms", ms)`.
+///
+/// The logger form formats with `format_args!`, so it does not allocate, and works
+/// without `std` or `critical-section`.
+///
+/// ```
+/// let capture = cand::CaptureStorage::new();
+/// let mut logger = cand::Logger((), capture.clone());
+/// cand::ok!(logger, "connected to {}", "10.0.0.2");
+/// cand::critical!(logger, "brownout at {}mV", 2900);
+/// // Compiled out when `STATIC_MAX_LEVEL` is above `Ok`.
+/// let kept = cand::level_enabled!(cand::StatusLevel::Ok);
+/// assert_eq!(capture.contains(cand::StatusLevel::Ok, "connected to 10.0.0.2"), kept);
+/// assert!(capture.contains(cand::StatusLevel::Critical, "brownout at 2900mV"));
+/// ```
+#[macro_export]
+macro_rules! ok {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::level_enabled!($crate::StatusLevel::Ok) {
+            $crate::log_global($crate::StatusLevel::Ok, format_args!($fmt $(, $arg)*))
+        }
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::Ok) {
+            $logger.logdisp($crate::StatusLevel::Ok, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Logs an `Info` message through the global logger, or through the logger given first.
+#[macro_export]
+macro_rules! info {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::level_enabled!($crate::StatusLevel::Info) {
+            $crate::log_global($crate::StatusLevel::Info, format_args!($fmt $(, $arg)*))
+        }
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::Info) {
+            $logger.logdisp($crate::StatusLevel::Info, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Logs a `Warning` message through the global logger, or through the logger given first.
+#[macro_export]
+macro_rules! warn {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::level_enabled!($crate::StatusLevel::Warning) {
+            $crate::log_global($crate::StatusLevel::Warning, format_args!($fmt $(, $arg)*))
+        }
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::Warning) {
+            $logger.logdisp($crate::StatusLevel::Warning, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Logs an `Error` message through the global logger, or through the logger given first.
+#[macro_export]
+macro_rules! error {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::level_enabled!($crate::StatusLevel::Error) {
+            $crate::log_global($crate::StatusLevel::Error, format_args!($fmt $(, $arg)*))
+        }
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::Error) {
+            $logger.logdisp($crate::StatusLevel::Error, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Logs a `Critical` message through the global logger, or through the logger given first.
+#[macro_export]
+macro_rules! crit {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::level_enabled!($crate::StatusLevel::Critical) {
+            $crate::log_global($crate::StatusLevel::Critical, format_args!($fmt $(, $arg)*))
+        }
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::Critical) {
+            $logger.logdisp($crate::StatusLevel::Critical, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Same as [`crit!`].
+#[macro_export]
+macro_rules! critical {
+    ($($arg:tt)+) => {
+        $crate::crit!($($arg)+)
+    };
 }
 
-#[cfg(feature = "ufmt")]
-struct UDebugDuration(Duration);
-
-#[cfg(feature = "ufmt")]
-impl uDebug for UDebugDuration {
-    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
-    where
-        W: uWrite + ?Sized,
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_failed(message: impl Display) -> ! {
+    #[cfg(feature = "std")]
+    panic!("assertion failed: {message}");
+    #[cfg(not(feature = "std"))]
     {
-        use ufmt::uwrite;
+        let _ = message;
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+}
 
-        let nanos = self.0.as_nanos();
-        let micros = self.0.as_micros();
-        let millis = self.0.as_millis();
-        let secs = self.0.as_secs();
-
-        if nanos < 1_000 {
-            uwrite!(f, "{}ns", nanos as u32)
-        } else if micros < 1_000 {
-            uwrite!(f, "{}μs", micros as u32)
-        } else if millis < 1_000 {
-            uwrite!(f, "{}ms", millis as u32)
-        } else if secs < 60 {
-            let remaining_millis = (millis % 1000) as u32;
-            uwrite!(f, "{}.{}s", secs, remaining_millis)
-        } else if secs < 3600 {
-            let mins = secs / 60;
-            let remaining_secs = secs % 60;
-            uwrite!(f, "{}:{}min", mins, remaining_secs)
-        } else if secs < 86400 {
-            let hours = secs / 3600;
-            let mins = (secs % 3600) / 60;
-            let remaining_secs = secs % 60;
-            uwrite!(f, "{}:{}:{}", hours, mins, remaining_secs)
-        } else {
-            let days = secs / 86400;
-            let hours = (secs % 86400) / 3600;
-            if hours > 0 {
-                uwrite!(f, "{}d{}h", days, hours)
-            } else {
-                uwrite!(f, "{}d", days)
+/// Like [`assert_eq!`], but reports through [`log_assert`](Logger::log_assert):
+/// `log_assert_eq!(logger, len, 4, "bad frame")` logs
+/// ``assertion failed: `left == right`: bad frame (left: 3, right: 4)``.
+///
+/// ```should_panic
+/// let mut logger = cand::Logger((), cand::CaptureStorage::new());
+/// let len = 3;
+/// cand::log_assert_eq!(logger, len, 4, "bad frame");
+/// ```
+#[macro_export]
+macro_rules! log_assert_eq {
+    ($logger:expr, $left:expr, $right:expr $(,)?) => {
+        $crate::log_assert_eq!($logger, $left, $right, "values differ")
+    };
+    ($logger:expr, $left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $logger.log_assert(
+                        false,
+                        format_args!(
+                            "`left == right`: {} (left: {:?}, right: {:?})",
+                            format_args!($($arg)+),
+                            left,
+                            right
+                        ),
+                    );
+                }
             }
         }
-    }
+    };
 }
 
-#[cfg(feature = "ufmt")]
-macro_rules! impl_log_methods_ufmt {
-    ($($method:ident => $level:expr),* $(,)?) => {
-        $(
-            pub fn $method(&mut self, args: &str) {
-                self.logdisp($level, args);
+/// Like [`assert_ne!`], but reports through [`log_assert`](Logger::log_assert).
+#[macro_export]
+macro_rules! log_assert_ne {
+    ($logger:expr, $left:expr, $right:expr $(,)?) => {
+        $crate::log_assert_ne!($logger, $left, $right, "values are equal")
+    };
+    ($logger:expr, $left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $logger.log_assert(
+                        false,
+                        format_args!(
+                            "`left != right`: {} (left: {:?}, right: {:?})",
+                            format_args!($($arg)+),
+                            left,
+                            right
+                        ),
+                    );
+                }
             }
-        )*
+        }
     };
 }
 
-#[cfg(feature = "std")]
-#[cfg(feature = "ufmt")]
-struct StdWriter<'a>(&'a mut dyn std::io::Write);
+/// Identifies a message for [`Logger::log_once`]: a `u64`, or a `&'static str`
+/// hashed with FNV-1a.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OnceKey(pub u64);
 
-#[cfg(feature = "std")]
-#[cfg(feature = "ufmt")]
-impl<'a> uWrite for StdWriter<'a> {
-    type Error = ();
-    fn write_str(&mut self, s: &str) -> Result<(), ()> {
-        self.0.write_all(s.as_bytes()).map_err(|_| ())
+impl From<u64> for OnceKey {
+    fn from(key: u64) -> Self {
+        OnceKey(key)
     }
 }
 
-#[cfg(feature = "std")]
-#[cfg(feature = "ufmt")]
-impl UStorageProvider for () {
-    fn write_data(&mut self, d: impl uDebug) {
-        use std::io::{self};
-        let mut stdout = io::stdout();
-        let mut writer = StdWriter(&mut stdout);
-        let _ = d.fmt(&mut ufmt::Formatter::new(&mut writer));
+impl From<&'static str> for OnceKey {
+    fn from(key: &'static str) -> Self {
+        OnceKey(key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+        }))
     }
 }
 
-#[cfg(feature = "ufmt")]
-pub struct ULogger<T: TimeProvider, S: UStorageProvider>(pub T, pub S);
-
-#[cfg(feature = "ufmt")]
-#[derive(Clone)]
-pub struct MultiULogger<T: TimeProvider + Clone, S: UStorageProvider + Clone>(pub T, pub S);
+/// Whether `key` is seen for the first time in this program run.
+#[cfg(feature = "std")]
+fn first_time(key: OnceKey) -> bool {
+    static SEEN: std::sync::Mutex<std::collections::BTreeSet<OnceKey>> =
+        std::sync::Mutex::new(std::collections::BTreeSet::new());
+    SEEN.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(key)
+}
 
-#[cfg(feature = "ufmt")]
-impl<T: TimeProvider, S: UStorageProvider> ULogger<T, S> {
-    pub fn log(&mut self, level: StatusLevel, args: impl uDebug) {
-        let timestamp = self.0.elapsed();
-        self.1.write_data(level);
-        self.1.write_data(UDebugDuration(timestamp));
-        self.1.write_data(UDebugStr(level.to_color()));
-        self.1.write_data(args);
-        self.1.write_data(UDebugStr(RESET));
-        self.1.write_data(UDebugStr("\n"));
+/// Whether `key` is seen for the first time in this program run.
+///
+/// Keys go in a fixed table of [`ONCE_SLOTS`] slots; once it is full, new keys
+/// are always logged.
+#[cfg(not(feature = "std"))]
+fn first_time(key: OnceKey) -> bool {
+    use core::sync::atomic::{
+        AtomicU8, AtomicU32,
+        Ordering::{Acquire, Relaxed, Release},
+    };
+    const EMPTY: u8 = 0;
+    const CLAIMED: u8 = 1;
+    const READY: u8 = 2;
+    // Split in two halves so targets without 64-bit atomics can hold a key.
+    struct Slot {
+        state: AtomicU8,
+        hi: AtomicU32,
+        lo: AtomicU32,
     }
+    static SLOTS: [Slot; ONCE_SLOTS] = [const {
+        Slot {
+            state: AtomicU8::new(EMPTY),
+            hi: AtomicU32::new(0),
+            lo: AtomicU32::new(0),
+        }
+    }; ONCE_SLOTS];
 
-    pub fn logdisp(&mut self, level: StatusLevel, args: &str) {
-        let timestamp = self.0.elapsed();
-        self.1.write_data(level);
-        self.1.write_data(UDebugDuration(timestamp));
-        self.1.write_data(UDebugStr(level.to_color()));
-        self.1.write_data(UDebugStr(args));
-        self.1.write_data(UDebugStr(RESET));
-        self.1.write_data(UDebugStr("\n"));
+    let (hi, lo) = ((key.0 >> 32) as u32, key.0 as u32);
+    let start = (key.0 % ONCE_SLOTS as u64) as usize;
+    for i in 0..ONCE_SLOTS {
+        let slot = &SLOTS[(start + i) % ONCE_SLOTS];
+        match slot.state.load(Acquire) {
+            READY if slot.hi.load(Relaxed) == hi && slot.lo.load(Relaxed) == lo => return false,
+            EMPTY => {
+                #[cfg(target_has_atomic = "8")]
+                let claimed = slot
+                    .state
+                    .compare_exchange(EMPTY, CLAIMED, Relaxed, Relaxed)
+                    .is_ok();
+                // Without compare-and-swap two racing callers may claim the same slot.
+                #[cfg(not(target_has_atomic = "8"))]
+                let claimed = {
+                    slot.state.store(CLAIMED, Relaxed);
+                    true
+                };
+                if claimed {
+                    slot.hi.store(hi, Relaxed);
+                    slot.lo.store(lo, Relaxed);
+                    slot.state.store(READY, Release);
+                    return true;
+                }
+            }
+            // Being claimed by a caller we may have interrupted, so don't wait on it;
+            // at worst a key racing with itself is logged twice.
+            _ => {}
+        }
     }
+    true
+}
 
-    impl_log_methods_ufmt! {
-        log_err => StatusLevel::Error,
-        log_ok => StatusLevel::Ok,
-        log_warn => StatusLevel::Warning,
-        log_info => StatusLevel::Info,
-    }
+/// How many distinct keys [`Logger::log_once`] remembers without std.
+#[cfg(not(feature = "std"))]
+const ONCE_SLOTS: usize = 64;
 
-    #[cfg(feature = "alloc")]
-    #[cfg(not(feature = "ufmt"))]
-    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
-        if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, err);
-        }
+#[doc(hidden)]
+pub fn __first_call(done: &core::sync::atomic::AtomicBool) -> bool {
+    use core::sync::atomic::Ordering::Relaxed;
+    if done.load(Relaxed) {
+        return false;
     }
-
-    #[cfg(feature = "alloc")]
-    #[cfg(not(feature = "ufmt"))]
-    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
-    where
-        F: FnOnce(O),
+    #[cfg(target_has_atomic = "8")]
+    return !done.swap(true, Relaxed);
+    // Without compare-and-swap two racing callers may both get through.
+    #[cfg(not(target_has_atomic = "8"))]
     {
-        match tryresult {
-            Ok(ok) => value(ok),
-            Err(err) => self.log(StatusLevel::Error, err),
-        }
+        done.store(true, Relaxed);
+        true
     }
+}
 
-    #[cfg(feature = "alloc")]
-    #[cfg(feature = "ufmt")]
-    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
-        if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, UDebugStr(&err.to_string()));
+#[doc(hidden)]
+pub fn __every_n(calls: &core::sync::atomic::AtomicU32, n: u32) -> bool {
+    use core::sync::atomic::Ordering::Relaxed;
+    #[cfg(target_has_atomic = "32")]
+    let count = calls.fetch_add(1, Relaxed);
+    #[cfg(not(target_has_atomic = "32"))]
+    let count = {
+        let count = calls.load(Relaxed);
+        calls.store(count.wrapping_add(1), Relaxed);
+        count
+    };
+    count.is_multiple_of(n.max(1))
+}
+
+/// Logs only the first time this call site is reached, through the global
+/// logger or the logger given first:
+/// `log_once!(StatusLevel::Warning, "clock not set")`,
+/// `log_once!(logger, StatusLevel::Warning, "clock not set")`.
+///
+/// Each call site keeps a `static AtomicBool`; later calls cost one atomic load
+/// and do not evaluate the arguments. On targets without compare-and-swap two
+/// threads racing on the first call may both log.
+///
+/// ```
+/// use cand::{CaptureStorage, Logger, StatusLevel};
+///
+/// let capture = CaptureStorage::new();
+/// let mut logger = Logger((), capture.clone());
+/// for frame in 0..250 {
+///     cand::log_once!(logger, StatusLevel::Critical, "clock not set");
+///     cand::log_every_n!(logger, 100, StatusLevel::Critical, "processed {} frames", frame);
+/// }
+/// let messages = capture.messages();
+/// let texts: Vec<_> = messages.iter().map(|(_, text)| text.as_str()).collect();
+/// assert_eq!(
+///     texts,
+///     ["clock not set", "processed 0 frames", "processed 100 frames", "processed 200 frames"]
+/// );
+/// ```
+#[macro_export]
+macro_rules! log_once {
+    ($level:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        static DONE: ::core::sync::atomic::AtomicBool = ::core::sync::atomic::AtomicBool::new(false);
+        let level: $crate::StatusLevel = $level;
+        if $crate::StatusLevel::severity(&level) >= $crate::STATIC_MAX_LEVEL.severity() && $crate::__first_call(&DONE) {
+            $crate::log_global(level, format_args!($fmt $(, $arg)*));
         }
-    }
+    }};
+    ($logger:expr, $level:expr, $($arg:tt)+) => {{
+        static DONE: ::core::sync::atomic::AtomicBool = ::core::sync::atomic::AtomicBool::new(false);
+        let level: $crate::StatusLevel = $level;
+        if $crate::StatusLevel::severity(&level) >= $crate::STATIC_MAX_LEVEL.severity() && $crate::__first_call(&DONE) {
+            $logger.logdisp(level, format_args!($($arg)+));
+        }
+    }};
+}
 
-    #[cfg(feature = "alloc")]
-    #[cfg(feature = "ufmt")]
-    pub fn try_run_get<O, F>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>, value: F)
-    where
-        F: FnOnce(O),
-    {
-        if let Err(err) = tryresult {
-            match tryresult {
-                Ok(ok) => value(ok),
-                Err(err) => self.log(StatusLevel::Error, UDebugStr(&err.to_string())),
-            }
+/// [`log_once!`] at `Warning`: `warn_once!("clock not set")` or `warn_once!(logger, "clock not set")`.
+#[macro_export]
+macro_rules! warn_once {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::log_once!($crate::StatusLevel::Warning, $fmt $(, $arg)*)
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::log_once!($logger, $crate::StatusLevel::Warning, $($arg)+)
+    };
+}
+
+/// Logs on the first and then every `n`th time this call site is reached:
+/// `log_every_n!(100, StatusLevel::Info, "processed {} frames", n)`, or with
+/// the logger given first. See [`log_once!`] for the cost when skipped.
+#[macro_export]
+macro_rules! log_every_n {
+    ($n:expr, $level:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        static CALLS: ::core::sync::atomic::AtomicU32 = ::core::sync::atomic::AtomicU32::new(0);
+        let level: $crate::StatusLevel = $level;
+        if $crate::StatusLevel::severity(&level) >= $crate::STATIC_MAX_LEVEL.severity() && $crate::__every_n(&CALLS, $n) {
+            $crate::log_global(level, format_args!($fmt $(, $arg)*));
         }
-    }
+    }};
+    ($logger:expr, $n:expr, $level:expr, $($arg:tt)+) => {{
+        static CALLS: ::core::sync::atomic::AtomicU32 = ::core::sync::atomic::AtomicU32::new(0);
+        let level: $crate::StatusLevel = $level;
+        if $crate::StatusLevel::severity(&level) >= $crate::STATIC_MAX_LEVEL.severity() && $crate::__every_n(&CALLS, $n) {
+            $logger.logdisp(level, format_args!($($arg)+));
+        }
+    }};
+}
 
-    #[cfg(not(feature = "alloc"))]
-    pub fn try_run<O, E: ufmt::uDebug>(&mut self, tryresult: Result<O, E>) {
-        if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, err);
+/// The current `file!()` and `line!()` as a `&'static` [`SourceLocation`].
+#[macro_export]
+macro_rules! here {
+    () => {
+        &$crate::SourceLocation {
+            file: file!(),
+            line: line!(),
         }
-    }
-    #[cfg(not(feature = "alloc"))]
-    pub fn try_run_get<O, E: ufmt::uDebug, F>(&mut self, tryresult: Result<O, E>, value: F)
-    where
-        F: FnOnce(O),
-    {
-        if let Err(err) = tryresult {
-            match tryresult {
-                Ok(ok) => value(ok),
-                Err(err) => self.log(StatusLevel::Error, err),
-            }
+    };
+}
+
+/// Logs a formatted message with the call site after it, e.g.
+/// `log!(logger, Warning, "retry {}", n)` writes `retry 3 (src/main.rs:42)`.
+///
+/// The level is a [`StatusLevel`] variant name. Goes through `log_at`, so it
+/// works with [`Logger`] and [`MultiLogger`] and does not allocate; for
+/// `ULogger` call `log_at` with [`here!`].
+#[macro_export]
+macro_rules! log {
+    ($logger:expr, $level:ident, $($arg:tt)+) => {
+        if $crate::level_enabled!($crate::StatusLevel::$level) {
+            $logger.log_at(
+                $crate::StatusLevel::$level,
+                $crate::here!(),
+                format_args!($($arg)+),
+            )
         }
-    }
+    };
+}
 
-    impl_try_get!(ufmt::uDebug, owned);
+/// [`log!`] with the level as an expression, e.g.
+/// `cand_log!(logger, StatusLevel::Warning, "failed: {}", err)` writes
+/// `failed: ... (src/main.rs:12)` through `log_at`.
+///
+/// ```
+/// use cand::{strip_ansi, Logger, StatusLevel, VecStorage};
+///
+/// let mut logger = Logger((), VecStorage::new());
+/// let level = StatusLevel::Critical;
+/// cand::cand_log!(logger, level, "failed: {}", "timeout");
+/// let expected = format!("C&: failed: timeout ({}:{})\n", file!(), line!() - 1);
+/// assert_eq!(strip_ansi(logger.1.as_str()), expected);
+/// ```
+#[macro_export]
+macro_rules! cand_log {
+    ($logger:expr, $level:expr, $($arg:tt)+) => {{
+        let level: $crate::StatusLevel = $level;
+        if $crate::StatusLevel::severity(&level) >= $crate::STATIC_MAX_LEVEL.severity() {
+            $logger.log_at(level, $crate::here!(), format_args!($($arg)+))
+        }
+    }};
 }
 
-#[cfg(feature = "ufmt")]
-impl<T: TimeProvider + Clone, S: UStorageProvider + Clone> MultiULogger<T, S>
-where
-    Self: Clone,
-{
-    pub fn log(&mut self, level: StatusLevel, args: impl uDebug) {
-        let timestamp = self.0.elapsed();
-        self.1.write_data(level);
-        self.1.write_data(UDebugDuration(timestamp));
-        self.1.write_data(UDebugStr(level.to_color()));
-        self.1.write_data(args);
-        self.1.write_data(UDebugStr(RESET));
-        self.1.write_data(UDebugStr("\n"));
-    }
+/// Formats a panic for logging, as used by [`black_box_cand!`]:
+/// `panicked at src/main.rs:3:5:` followed by the message in the `Critical` color.
+///
+/// Colors follow the `colors` feature, [`ColorMode`] and [`ColorTheme`].
+///
+/// ```
+/// use std::sync::Mutex;
+/// use cand::{format_panic, set_color_mode, ColorMode};
+///
+/// static MESSAGE: Mutex<String> = Mutex::new(String::new());
+///
+/// set_color_mode(ColorMode::Never);
+/// std::panic::set_hook(Box::new(|info| *MESSAGE.lock().unwrap() = format_panic(info).to_string()));
+/// let _ = std::panic::catch_unwind(|| panic!("config: missing key"));
+/// let _ = std::panic::take_hook();
+///
+/// let message = MESSAGE.lock().unwrap();
+/// assert!(message.starts_with(&format!("panicked at {}:", file!())));
+/// assert!(message.ends_with("\nconfig: missing key"));
+/// assert!(!message.contains('\x1b'));
+/// ```
+#[cfg(feature = "std")]
+pub fn format_panic<'a>(info: &'a std::panic::PanicHookInfo<'a>) -> impl Display + 'a {
+    PanicMessage(info)
+}
 
-    pub fn logdisp(&mut self, level: StatusLevel, args: &str) {
-        let timestamp = self.0.elapsed();
-        self.1.write_data(level);
-        self.1.write_data(UDebugDuration(timestamp));
-        self.1.write_data(UDebugStr(level.to_color()));
-        self.1.write_data(UDebugStr(args));
-        self.1.write_data(UDebugStr(RESET));
-        self.1.write_data(UDebugStr("\n"));
+/// The message of a panic payload, for `panic!` with a literal or a formatted message.
+#[cfg(feature = "std")]
+fn payload_to_str(payload: &(dyn core::any::Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&'static str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "unknown panic payload"
     }
+}
 
-    impl_log_methods_ufmt! {
-        log_err => StatusLevel::Error,
-        log_ok => StatusLevel::Ok,
-        log_warn => StatusLevel::Warning,
-        log_info => StatusLevel::Info,
-    }
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Set while [`Logger::catch`] runs a closure on this thread.
+    static PANIC_HOOK_SUPPRESSED: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+}
 
-    #[cfg(feature = "alloc")]
-    pub fn try_run<O>(&mut self, tryresult: Result<O, Box<dyn core::error::Error>>) {
-        if let Err(err) = tryresult {
-            self.log(StatusLevel::Error, UDebugStr(&err.to_string()));
-        }
-    }
+/// Whether a panic on this thread is being caught by [`Logger::catch`], so panic
+/// hooks should stay quiet.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __panic_suppressed() -> bool {
+    PANIC_HOOK_SUPPRESSED.with(core::cell::Cell::get)
+}
 
-    #[cfg(feature = "alloc")]
-    pub fn try_run_get<O>(
-        &mut self,
-        tryresult: Result<O, Box<dyn core::error::Error>>,
-        value: fn(O) -> (),
-    ) {
-        match tryresult {
-            Ok(ok) => value(o),
-            Err(err) => self.log(StatusLevel::Error, UDebugStr(&err.to_string())),
+/// `catch_unwind` with the panic hook skipped for panics on the calling thread.
+///
+/// The first call wraps the installed hook, once, in one that stays quiet while
+/// [`__panic_suppressed`] is set; the cand panic hooks check the flag as well, so
+/// installing one later keeps `catch` quiet.
+#[cfg(feature = "std")]
+fn catch_quietly<R>(f: impl FnOnce() -> R + std::panic::UnwindSafe) -> std::thread::Result<R> {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !__panic_suppressed() {
+                previous(info);
+            }
+        }));
+    });
+    let outer = PANIC_HOOK_SUPPRESSED.with(|suppressed| suppressed.replace(true));
+    let result = std::panic::catch_unwind(f);
+    PANIC_HOOK_SUPPRESSED.with(|suppressed| suppressed.set(outer));
+    result
+}
+
+#[cfg(feature = "std")]
+struct PanicMessage<'a>(&'a std::panic::PanicHookInfo<'a>);
+
+#[cfg(feature = "std")]
+impl Display for PanicMessage<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let payload = payload_to_str(self.0.payload());
+        let (before, after) = match payload.find(": ") {
+            Some(pos) => payload.split_at(pos + 2),
+            None => ("", payload),
+        };
+        write!(f, "{}panicked at ", reset())?;
+        match self.0.location() {
+            Some(location) => write!(
+                f,
+                "{}:{}:{}:",
+                location.file(),
+                location.line(),
+                location.column()
+            )?,
+            None => f.write_str("unknown location:")?,
         }
+        let color = StatusLevel::Critical.to_color();
+        write!(f, "\n{}{}{}{}{}", reset(), before, color, after, reset())
     }
+}
 
-    impl_try_get!(ufmt::uDebug, cloned);
+/// Captures a backtrace for the panic hooks, formatted as text.
+///
+/// `None` unless `force` is set or `RUST_BACKTRACE` enables capturing.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __panic_backtrace(force: bool) -> Option<String> {
+    let backtrace = if force {
+        std::backtrace::Backtrace::force_capture()
+    } else {
+        std::backtrace::Backtrace::capture()
+    };
+    if backtrace.status() != std::backtrace::BacktraceStatus::Captured {
+        return None;
+    }
+    // Formatting into a String cannot fail, and errors are ignored rather than
+    // panicking inside the panic hook.
+    let mut text = String::new();
+    let _ = core::fmt::Write::write_fmt(&mut text, format_args!("{backtrace}"));
+    Some(text)
 }
 
+/// Sets a panic hook that logs the panic at `Critical` level.
+///
+/// With `backtrace` as the first argument a backtrace is always logged after the
+/// message, one line per log call; otherwise only when `RUST_BACKTRACE` is set.
+///
+/// `black_box_cand!()`, `black_box_cand!(logger)`, `black_box_cand!(backtrace)`
+/// or `black_box_cand!(backtrace, logger)`.
+///
+/// ```
+/// use std::sync::LazyLock;
+/// use cand::{CaptureStorage, Logger, StatusLevel};
+///
+/// static CAPTURE: LazyLock<CaptureStorage> = LazyLock::new(CaptureStorage::new);
+///
+/// cand::black_box_cand!(backtrace, Logger((), CAPTURE.clone()));
+/// let _ = std::panic::catch_unwind(|| panic!("sensor {} missing", 3));
+/// let _ = std::panic::take_hook();
+///
+/// assert!(CAPTURE.contains(StatusLevel::Critical, "sensor 3 missing"));
+/// let lines = CAPTURE.messages();
+/// assert!(lines.iter().all(|(level, _)| *level == StatusLevel::Critical));
+/// assert!(lines.iter().any(|(_, line)| line.trim_start().starts_with("at ")));
+/// ```
 #[cfg(feature = "std")]
 #[macro_export]
 macro_rules! black_box_cand {
     () => {
+        $crate::black_box_cand!(@hook false, $crate::Logger(::std::time::Instant::now(), ()))
+    };
+
+    (backtrace) => {
+        $crate::black_box_cand!(@hook true, $crate::Logger(::std::time::Instant::now(), ()))
+    };
+
+    (backtrace, $logger_expr:expr) => {
+        $crate::black_box_cand!(@hook true, $logger_expr)
+    };
+
+    (@hook $force_backtrace:expr, $logger_expr:expr) => {
         ::std::panic::set_hook(Box::new(|info| {
-            let mut logger = $crate::Logger(::std::time::Instant::now(), ());
-            let payload = if let Some(s) = info.payload().downcast_ref::<&'static str>() {
-                *s
-            } else if let Some(s) = info.payload().downcast_ref::<String>() {
-                s.as_str()
-            } else {
-                "unknown panic payload"
-            };
-            let (before, after) = if let Some(pos) = payload.find(": ") {
-                (&payload[0..pos + 2], &payload[pos + 2..])
-            } else {
-                ("", payload)
-            };
-            let message = if let Some(location) = info.location() {
-                format!(
-                    "\x1b[0mpanicked at {}:{}:{}:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    location.file(),
-                    location.line(),
-                    location.column(),
-                    before,
-                    after
-                )
-            } else {
-                format!(
-                    "\x1b[0mpanicked at unknown location:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    before, after
-                )
-            };
+            if $crate::__panic_suppressed() {
+                return;
+            }
+            let mut logger = $logger_expr;
+            let message = $crate::format_panic(info).to_string();
+            let backtrace = $crate::__panic_backtrace($force_backtrace);
             logger.logdisp($crate::StatusLevel::Critical, &message);
-        }));
+            for line in backtrace.iter().flat_map(|backtrace| backtrace.lines()) {
+                logger.logdisp($crate::StatusLevel::Critical, line);
+            }
+        }))
     };
 
     ($logger_expr:expr) => {
-        ::std::panic::set_hook(Box::new(|info| {
+        $crate::black_box_cand!(@hook false, $logger_expr)
+    };
+}
+
+/// Like [`black_box_cand!`], but keeps the previously installed panic hook (e.g. a
+/// crash reporter) and calls it after cand has logged the panic.
+///
+/// `black_box_cand_chain!()` or `black_box_cand_chain!(logger)`.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::LazyLock;
+/// use cand::{CaptureStorage, Logger, StatusLevel};
+///
+/// static CAPTURE: LazyLock<CaptureStorage> = LazyLock::new(CaptureStorage::new);
+/// static REPORTED: AtomicUsize = AtomicUsize::new(0);
+///
+/// // A crash reporter's hook, which only runs after cand has logged.
+/// std::panic::set_hook(Box::new(|_| {
+///     assert_eq!(CAPTURE.len(), 1);
+///     REPORTED.fetch_add(1, Ordering::Relaxed);
+/// }));
+/// cand::black_box_cand_chain!(Logger((), CAPTURE.clone()));
+/// let _ = std::panic::catch_unwind(|| panic!("watchdog"));
+/// let _ = std::panic::take_hook();
+///
+/// assert!(CAPTURE.contains(StatusLevel::Critical, "watchdog"));
+/// assert_eq!(REPORTED.load(Ordering::Relaxed), 1);
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! black_box_cand_chain {
+    () => {
+        $crate::black_box_cand_chain!($crate::Logger(::std::time::Instant::now(), ()))
+    };
+
+    ($logger_expr:expr) => {{
+        let previous = ::std::panic::take_hook();
+        ::std::panic::set_hook(Box::new(move |info| {
+            if $crate::__panic_suppressed() {
+                return;
+            }
             let mut logger = $logger_expr;
-            let payload = if let Some(s) = info.payload().downcast_ref::<&'static str>() {
-                *s
-            } else if let Some(s) = info.payload().downcast_ref::<String>() {
-                s.as_str()
-            } else {
-                "unknown panic payload"
-            };
-            let (before, after) = if let Some(pos) = payload.find(": ") {
-                (&payload[0..pos + 2], &payload[pos + 2..])
-            } else {
-                ("", payload)
-            };
-            let message = if let Some(location) = info.location() {
-                format!(
-                    "\x1b[0mpanicked at {}:{}:{}:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    location.file(),
-                    location.line(),
-                    location.column(),
-                    before,
-                    after
-                )
-            } else {
-                format!(
-                    "\x1b[0mpanicked at unknown location:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    before, after
-                )
-            };
+            let message = $crate::format_panic(info).to_string();
             logger.logdisp($crate::StatusLevel::Critical, &message);
+            previous(info);
         }))
-    };
+    }};
 }
 
+/// Like [`black_box_cand!`] for a logger shared behind a `Mutex`, e.g. a
+/// `static LOGGER: Lazy<Mutex<Logger<..>>>`; pass `backtrace` first to always log one.
 #[cfg(feature = "std")]
 #[macro_export]
 macro_rules! black_box_cand_global {
-    ($logger:expr) => {
+    (backtrace, $logger:expr) => {
+        $crate::black_box_cand_global!(@hook true, $logger)
+    };
+
+    (@hook $force_backtrace:expr, $logger:expr) => {
         let mut logger = $logger;
         ::std::panic::set_hook(Box::new(move |info| {
-            let payload = if let Some(s) = info.payload().downcast_ref::<&'static str>() {
-                *s
-            } else if let Some(s) = info.payload().downcast_ref::<String>() {
-                s.as_str()
-            } else {
-                "unknown panic payload"
-            };
-            let (before, after) = if let Some(pos) = payload.find(": ") {
-                (&payload[0..pos + 2], &payload[pos + 2..])
-            } else {
-                ("", payload)
-            };
-            let message = if let Some(location) = info.location() {
-                format!(
-                    "\x1b[0mpanicked at {}:{}:{}:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    location.file(),
-                    location.line(),
-                    location.column(),
-                    before,
-                    after
-                )
-            } else {
-                format!(
-                    "\x1b[0mpanicked at unknown location:\n\x1b[0m{}\x1b[31m{}\x1b[0m",
-                    before, after
-                )
-            };
+            if $crate::__panic_suppressed() {
+                return;
+            }
+            let message = $crate::format_panic(info).to_string();
+            let backtrace = $crate::__panic_backtrace($force_backtrace);
             if let Ok(mut guard) = logger.lock() {
-                guard.logdisp(cand::StatusLevel::Critical, &message);
+                guard.logdisp($crate::StatusLevel::Critical, &message);
+                for line in backtrace.iter().flat_map(|backtrace| backtrace.lines()) {
+                    guard.logdisp($crate::StatusLevel::Critical, line);
+                }
             }
         }));
     };
+
+    ($logger:expr) => {
+        $crate::black_box_cand_global!(@hook false, $logger)
+    };
 }